@@ -1,7 +1,10 @@
+use std::ops::Range;
+
 use anyhow::{anyhow, Result};
 use tracing::{debug, info, trace};
 
-use super::client::AzookeyConversionClient;
+use super::{client::AzookeyConversionClient, user_dictionary::UserDictionary};
+use crate::dictionary_matcher::convert_around;
 
 /// Maximum number of history entries to retain
 const MAX_HISTORY_SIZE: usize = 3;
@@ -37,6 +40,9 @@ pub struct AzookeyConversion {
 
     /// Client that performs conversion operations
     client: AzookeyConversionClient,
+
+    /// Byte ranges of pre-conversion dictionary outputs kept as they are
+    protected_spans: Vec<Range<usize>>,
 }
 
 impl AzookeyConversion {
@@ -59,9 +65,27 @@ impl AzookeyConversion {
             candidate_index: None,
             common_prefix: None,
             client,
+            protected_spans: Vec::new(),
         }
     }
 
+    /// Replaces the user dictionary words that conversion is split around
+    ///
+    /// # Arguments
+    /// * `user_dictionary` - Words built from the user dictionary
+    pub fn set_user_dictionary(&mut self, user_dictionary: UserDictionary) {
+        self.client.set_user_dictionary(user_dictionary);
+    }
+
+    /// Sets the text that the next conversion passes through unchanged
+    ///
+    /// # Arguments
+    /// * `protected_spans` - Byte ranges of pre-conversion dictionary outputs
+    ///   in the text passed to the next `convert`
+    pub fn set_protected_spans(&mut self, protected_spans: Vec<Range<usize>>) {
+        self.protected_spans = protected_spans;
+    }
+
     /// Converts text - Main entry point for conversion processing
     ///
     /// # Arguments
@@ -167,15 +191,19 @@ impl AzookeyConversion {
         let prefix = text.chars().take(first_diff_position).collect::<String>();
         trace!("Conversion prefix: {}", prefix);
 
-        // Use client for conversion
-        self.client.reset_composing_text();
-        self.client.insert_at_cursor_position(&diff_text);
-
-        // Get and select conversion candidate
-        let converted = match self.client.request_candidates("").first() {
-            Some(candidate) => candidate.text.clone(),
-            None => return Err(anyhow!("No conversion candidates available")),
-        };
+        // Use client for conversion, skipping dictionary outputs
+        let protected_spans = std::mem::take(&mut self.protected_spans);
+        let diff_offset = text.len() - diff_text.len();
+        let converted = convert_around(&diff_text, diff_offset, &protected_spans, |segment| {
+            self.client.reset_composing_text();
+            self.client.insert_at_cursor_position(segment);
+
+            // Get and select conversion candidate (user dictionary words come first)
+            match self.client.request_candidate_texts("").first() {
+                Some(candidate) => Ok(candidate.clone()),
+                None => Err(anyhow!("No conversion candidates available")),
+            }
+        })?;
         trace!("Conversion result: {}", converted);
 
         // Update history
@@ -260,13 +288,8 @@ impl AzookeyConversion {
         self.client.reset_composing_text();
         self.client.insert_at_cursor_position(diff_text);
 
-        // Get candidates from client
-        let mut candidates = self
-            .client
-            .request_candidates(prefix)
-            .iter()
-            .map(|c| c.text.clone())
-            .collect::<Vec<String>>();
+        // Get candidates from client, including user dictionary words
+        let mut candidates = self.client.request_candidate_texts(prefix);
         trace!("Retrieved candidates: {:?}", candidates);
 
         // Include raw text in candidates
@@ -9,7 +9,10 @@ use tracing::info;
 
 use crate::SELF_EXE_PATH;
 
-use super::IpcMessage;
+use super::{
+    user_dictionary::{convert_segments, Segment, UserDictionary},
+    IpcMessage,
+};
 
 static SIGNMAP: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
     HashMap::from([
@@ -50,6 +53,8 @@ pub struct AzookeyConversionClient {
     pub composing_text: ComposingText,
     pub extract_path: String,
     pub weight_path: String,
+    pub composing_input: String,
+    pub user_dictionary: UserDictionary,
 }
 
 impl AzookeyConversionClient {
@@ -77,6 +82,8 @@ impl AzookeyConversionClient {
             composing_text: ComposingText::new(),
             extract_path: extract_path.to_string(),
             weight_path,
+            composing_input: String::new(),
+            user_dictionary: UserDictionary::default(),
         }
     }
 
@@ -134,11 +141,13 @@ impl AzookeyConversionClient {
         info!("Resetting composing text");
 
         self.composing_text = ComposingText::new();
+        self.composing_input.clear();
     }
 
     pub fn insert_at_cursor_position(&mut self, text: &str) {
         info!("Inserting at cursor position: {}", text);
 
+        self.composing_input.push_str(text);
         let text = Self::pre_process_text(text);
         self.composing_text.insert_at_cursor_position(&text);
     }
@@ -156,4 +165,43 @@ impl AzookeyConversionClient {
         println!("{:?}", candidates);
         Self::post_process_candidates(candidates)
     }
+
+    pub fn set_user_dictionary(&mut self, user_dictionary: UserDictionary) {
+        self.user_dictionary = user_dictionary;
+    }
+
+    pub fn request_candidate_texts(&mut self, context: &str) -> Vec<String> {
+        let candidates = self
+            .request_candidates(context)
+            .iter()
+            .map(|c| c.text.clone())
+            .collect::<Vec<_>>();
+        let mut merged = self
+            .user_dictionary
+            .merge_candidates(&self.composing_input, candidates);
+        if let Some(segments) = self.user_dictionary.segment(&self.composing_input) {
+            let converted = self.convert_around_user_words(&segments, context);
+            merged.retain(|candidate| candidate != &converted);
+            merged.insert(0, converted);
+        }
+        info!("Candidates with user dictionary: {:?}", merged);
+        merged
+    }
+
+    /// Converts the text between user words on its own so that the engine never
+    /// rewrites the words, then restores the composing text
+    fn convert_around_user_words(&mut self, segments: &[Segment], context: &str) -> String {
+        let composing_input = self.composing_input.clone();
+        let converted = convert_segments(segments, context, |text, left_context| {
+            self.reset_composing_text();
+            self.insert_at_cursor_position(text);
+            self.request_candidates(left_context)
+                .into_iter()
+                .next()
+                .map(|candidate| candidate.text)
+        });
+        self.reset_composing_text();
+        self.insert_at_cursor_position(&composing_input);
+        converted
+    }
 }
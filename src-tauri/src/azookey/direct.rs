@@ -7,11 +7,16 @@ use tracing::info;
 
 use crate::SELF_EXE_PATH;
 
-use super::processing;
+use super::{
+    processing,
+    user_dictionary::{convert_segments, Segment, UserDictionary},
+};
 
 struct ConverterState {
     azookey_converter: KanaKanjiConverter,
     composing_text: ComposingText,
+    composing_input: String,
+    user_dictionary: UserDictionary,
     dictionary_path: String,
     weight_path: String,
 }
@@ -40,6 +45,8 @@ impl ConverterState {
         Self {
             azookey_converter: KanaKanjiConverter::new(),
             composing_text: ComposingText::new(),
+            composing_input: String::new(),
+            user_dictionary: UserDictionary::default(),
             dictionary_path,
             weight_path,
         }
@@ -60,11 +67,13 @@ impl DirectAzookeyConverter {
     pub fn reset_composing_text(&self) {
         let mut state = self.state.lock().unwrap();
         state.composing_text = ComposingText::new();
+        state.composing_input.clear();
     }
 
     pub fn insert_at_cursor_position(&self, text: &str) {
         let processed_text = processing::pre_process_text(text);
-        let state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        state.composing_input.push_str(text);
         state
             .composing_text
             .insert_at_cursor_position(&processed_text);
@@ -85,6 +94,50 @@ impl DirectAzookeyConverter {
         );
         processing::post_process_candidates(candidates)
     }
+
+    pub fn set_user_dictionary(&self, user_dictionary: UserDictionary) {
+        self.state.lock().unwrap().user_dictionary = user_dictionary;
+    }
+
+    pub fn request_candidate_texts(&self, context: &str) -> Vec<String> {
+        let candidates = self
+            .request_candidates(context)
+            .iter()
+            .map(|c| c.text.clone())
+            .collect::<Vec<_>>();
+        let (mut merged, segments) = {
+            let state = self.state.lock().unwrap();
+            (
+                state
+                    .user_dictionary
+                    .merge_candidates(&state.composing_input, candidates),
+                state.user_dictionary.segment(&state.composing_input),
+            )
+        };
+        if let Some(segments) = segments {
+            let converted = self.convert_around_user_words(&segments, context);
+            merged.retain(|candidate| candidate != &converted);
+            merged.insert(0, converted);
+        }
+        merged
+    }
+
+    /// Converts the text between user words on its own so that the engine never
+    /// rewrites the words, then restores the composing text
+    fn convert_around_user_words(&self, segments: &[Segment], context: &str) -> String {
+        let composing_input = self.state.lock().unwrap().composing_input.clone();
+        let converted = convert_segments(segments, context, |text, left_context| {
+            self.reset_composing_text();
+            self.insert_at_cursor_position(text);
+            self.request_candidates(left_context)
+                .into_iter()
+                .next()
+                .map(|candidate| candidate.text)
+        });
+        self.reset_composing_text();
+        self.insert_at_cursor_position(&composing_input);
+        converted
+    }
 }
 
 // Safety: The mutex ensures exclusive access to C FFI objects
@@ -4,7 +4,10 @@ use serde::{Deserialize, Serialize};
 
 pub mod azookey_conversion;
 pub mod client;
+pub mod direct;
+pub mod processing;
 pub mod server;
+pub mod user_dictionary;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum IpcMessage {
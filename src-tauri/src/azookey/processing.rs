@@ -40,16 +40,7 @@ pub static SIGNMAP: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::ne
 });
 
 pub fn pre_process_text(text: &str) -> String {
-    let mut result = String::new();
-
-    // replace all characters in the text with their corresponding replacements
-    for c in text.chars() {
-        if let Some(&replacement) = SIGNMAP.get(c.to_string().as_str()) {
-            result.push_str(replacement);
-        } else {
-            result.push(c);
-        }
-    }
+    let mut result = replace_signs(text);
 
     // push 'n' if the last and second last characters are 'n'
     if result.ends_with('n') {
@@ -66,6 +57,21 @@ pub fn pre_process_text(text: &str) -> String {
     result
 }
 
+pub fn replace_signs(text: &str) -> String {
+    let mut result = String::new();
+
+    // replace all characters in the text with their corresponding replacements
+    for c in text.chars() {
+        if let Some(&replacement) = SIGNMAP.get(c.to_string().as_str()) {
+            result.push_str(replacement);
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
 pub fn post_process_text(text: &str) -> String {
     let mut result = text.to_string();
 
@@ -0,0 +1,320 @@
+use std::cmp::Reverse;
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use tracing::{debug, error, trace};
+use wana_kana::ConvertJapanese;
+
+use crate::{
+    dictionary::{ConversionMethod, Dictionary, DictionaryStage},
+    dictionary_template,
+};
+
+/// Part of the composing input, either a user word or text left to the engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Word(String),
+    Text(String),
+}
+
+/// A reading -> surface pair registered from the user dictionary
+#[derive(Debug, Clone)]
+struct UserWord {
+    /// Reading normalized to hiragana
+    reading: String,
+    word: String,
+    priority: i32,
+}
+
+/// User dictionary words applied around the AzooKey engine
+///
+/// This is a workaround, not candidate injection: the AzooKey binding has no
+/// API for registering user words with `KanaKanjiConverter`, so the engine
+/// never sees them. Words are found in the composing input here, the text
+/// between them is converted by the engine on its own (see `segment` and
+/// `convert_segments`) and the results are joined. The engine therefore cannot
+/// use a word to segment or rank the text around it, and a word whose reading
+/// only appears after kana-kanji conversion is never found. A word is offered
+/// as a separate candidate only when it is the whole input (see
+/// `merge_candidates`). Registering the words properly needs support in the
+/// binding.
+///
+/// Only plain pre-conversion `Replace` entries are treated as words; regex
+/// entries and converter entries have no single reading to match against, and
+/// templated outputs are only meaningful once expanded at conversion time.
+#[derive(Debug, Clone, Default)]
+pub struct UserDictionary {
+    words: Vec<UserWord>,
+    /// Readings of `words`, matched leftmost-longest
+    readings: Option<AhoCorasick>,
+}
+
+impl UserDictionary {
+    /// Builds the word list from dictionary entries
+    ///
+    /// # Arguments
+    /// * `dictionary` - Dictionary edited in the Dictionary tab
+    ///
+    /// # Returns
+    /// * Words sorted by descending priority
+    pub fn from_dictionary(dictionary: &Dictionary) -> Self {
+        let mut words = dictionary
+            .entries
+            .iter()
+            .filter(|entry| {
+                !entry.use_regex
+                    && !entry.input.is_empty()
+                    && entry.stage == DictionaryStage::PreConversion
+            })
+            .filter_map(|entry| match (&entry.method, &entry.output) {
+                (ConversionMethod::Replace, Some(output))
+                    if !output.is_empty() && !dictionary_template::has_placeholders(output) =>
+                {
+                    Some(UserWord {
+                        reading: normalize_reading(&entry.input),
+                        word: output.clone(),
+                        priority: entry.priority,
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        words.sort_by_key(|word| Reverse(word.priority));
+
+        // Earlier patterns win between identical readings, so the highest
+        // priority word is used when segmenting
+        let readings = if words.is_empty() {
+            None
+        } else {
+            AhoCorasickBuilder::new()
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(words.iter().map(|word| &word.reading))
+                .map_err(|e| error!("Failed to build user dictionary automaton: {}", e))
+                .ok()
+        };
+
+        debug!("User dictionary built with {} words", words.len());
+        Self { words, readings }
+    }
+
+    /// Splits the composing input around user words
+    ///
+    /// # Arguments
+    /// * `input` - Romaji or kana input passed to the converter
+    ///
+    /// # Returns
+    /// * `Option<Vec<Segment>>` - Words and the hiragana text between them, or
+    ///   `None` if the input contains no user word
+    pub fn segment(&self, input: &str) -> Option<Vec<Segment>> {
+        let readings = self.readings.as_ref()?;
+        let reading = normalize_reading(input);
+        let mut segments = Vec::new();
+        let mut last_end = 0;
+        for m in readings.find_iter(&reading) {
+            if m.start() > last_end {
+                segments.push(Segment::Text(reading[last_end..m.start()].to_string()));
+            }
+            segments.push(Segment::Word(
+                self.words[m.pattern().as_usize()].word.clone(),
+            ));
+            last_end = m.end();
+        }
+        if segments.is_empty() {
+            return None;
+        }
+        if last_end < reading.len() {
+            segments.push(Segment::Text(reading[last_end..].to_string()));
+        }
+        trace!("User dictionary segments: {} -> {:?}", reading, segments);
+        Some(segments)
+    }
+
+    /// Looks up words whose reading matches the composing input
+    ///
+    /// # Arguments
+    /// * `input` - Romaji or kana input passed to the converter
+    ///
+    /// # Returns
+    /// * `Vec<String>` - Matching surface forms in priority order
+    pub fn lookup(&self, input: &str) -> Vec<String> {
+        let reading = normalize_reading(input);
+        let words = self
+            .words
+            .iter()
+            .filter(|word| word.reading == reading)
+            .map(|word| word.word.clone())
+            .collect::<Vec<_>>();
+        trace!("User dictionary lookup: {} -> {:?}", reading, words);
+        words
+    }
+
+    /// Places user words ahead of the engine candidates, removing duplicates
+    ///
+    /// # Arguments
+    /// * `input` - Romaji or kana input passed to the converter
+    /// * `candidates` - Candidate texts returned by AzooKey
+    ///
+    /// # Returns
+    /// * `Vec<String>` - Merged candidate texts
+    pub fn merge_candidates(&self, input: &str, candidates: Vec<String>) -> Vec<String> {
+        let mut merged = self.lookup(input);
+        for candidate in candidates {
+            if !merged.contains(&candidate) {
+                merged.push(candidate);
+            }
+        }
+        merged
+    }
+}
+
+fn normalize_reading(text: &str) -> String {
+    text.trim().to_lowercase().to_hiragana()
+}
+
+/// Joins user words with the engine conversion of the text between them
+///
+/// # Arguments
+/// * `segments` - Segments returned by `UserDictionary::segment`
+/// * `context` - Text before the composing input
+/// * `convert` - Converts a text segment given everything to its left
+///
+/// # Returns
+/// * `String` - The converted input; text the engine cannot convert is kept
+pub fn convert_segments(
+    segments: &[Segment],
+    context: &str,
+    mut convert: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+    let mut converted = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Word(word) => converted.push_str(word),
+            Segment::Text(text) => {
+                let left_context = format!("{}{}", context, converted);
+                converted.push_str(&convert(text, &left_context).unwrap_or_else(|| text.clone()));
+            }
+        }
+    }
+    debug!(
+        "Converted around user words: {:?} -> {}",
+        segments, converted
+    );
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::DictionaryEntry;
+
+    fn word(input: &str, output: &str, priority: i32) -> DictionaryEntry {
+        DictionaryEntry {
+            input: input.to_string(),
+            method: ConversionMethod::Replace,
+            output: Some(output.to_string()),
+            priority,
+            ..Default::default()
+        }
+    }
+
+    fn user_dictionary(entries: Vec<DictionaryEntry>) -> UserDictionary {
+        UserDictionary::from_dictionary(&Dictionary {
+            entries,
+            ..Default::default()
+        })
+    }
+
+    fn text(text: &str) -> Segment {
+        Segment::Text(text.to_string())
+    }
+
+    fn user_word(word: &str) -> Segment {
+        Segment::Word(word.to_string())
+    }
+
+    #[test]
+    fn segments_around_words() {
+        let dictionary = user_dictionary(vec![
+            word("kanata", "奏多", 0),
+            word("かな", "カナ", 0),
+            word("kanata", "彼方", 5),
+        ]);
+        let cases = [
+            (
+                "watashihakanatadesu",
+                Some(vec![text("わたしは"), user_word("彼方"), text("です")]),
+            ),
+            ("かなた", Some(vec![user_word("彼方")])),
+            ("kanakana", Some(vec![user_word("カナ"), user_word("カナ")])),
+            ("kanai", Some(vec![user_word("カナ"), text("い")])),
+            ("konnichiha", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(dictionary.segment(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn skips_entries_that_are_not_words() {
+        let dictionary = user_dictionary(vec![
+            DictionaryEntry {
+                use_regex: true,
+                ..word("kana", "カナ", 0)
+            },
+            DictionaryEntry {
+                stage: DictionaryStage::PostConversion,
+                ..word("kana", "カナ", 0)
+            },
+            DictionaryEntry {
+                method: ConversionMethod::Converter('k'),
+                ..word("kana", "カナ", 0)
+            },
+            word("kana", "{time}", 0),
+            word("kana", "", 0),
+            word("", "カナ", 0),
+        ]);
+        assert_eq!(dictionary.segment("kana"), None);
+        assert!(dictionary.lookup("kana").is_empty());
+    }
+
+    #[test]
+    fn merges_candidates() {
+        let dictionary =
+            user_dictionary(vec![word("kanata", "奏多", 0), word("kanata", "彼方", 5)]);
+        let cases = [
+            (
+                "kanata",
+                vec!["彼方", "かなた"],
+                vec!["彼方", "奏多", "かなた"],
+            ),
+            ("かなた", vec![], vec!["彼方", "奏多"]),
+            ("kanatade", vec!["彼方で"], vec!["彼方で"]),
+        ];
+        for (input, candidates, expected) in cases {
+            let candidates = candidates.into_iter().map(String::from).collect();
+            assert_eq!(
+                dictionary.merge_candidates(input, candidates),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn converts_text_between_words() {
+        let segments = [text("わたしは"), user_word("奏多"), text("です")];
+        let mut calls = Vec::new();
+        let converted = convert_segments(&segments, "ねえ", |text, left_context| {
+            calls.push((text.to_string(), left_context.to_string()));
+            (text == "わたしは").then(|| "私は".to_string())
+        });
+        assert_eq!(converted, "私は奏多です");
+        assert_eq!(
+            calls,
+            [
+                ("わたしは".to_string(), "ねえ".to_string()),
+                ("です".to_string(), "ねえ私は奏多".to_string()),
+            ]
+        );
+    }
+}
@@ -1,68 +1,222 @@
+use std::sync::{Arc, Mutex};
+
 use crate::{
     config::Config,
-    converter::converter::{get_custom_converter, Converter},
-    STATE,
+    conversion_syntax::{tokenize, CommandStage, SyntaxError, Token},
+    converter::{
+        calculator::CalculatorSession,
+        converter::{converter_command_names, get_custom_converter, ConversionContext, Converter},
+        none_converter::NoneConverter,
+        unknown::UnknownConverter,
+    },
+    dictionary::DictionaryStage,
+    dictionary_matcher::{CompiledDictionary, MatchedSegment},
+    COMPILED_DICTIONARY, STATE,
 };
 use anyhow::Result;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
+
+/// One converter of a block together with its command arguments
+pub struct ConversionStage {
+    pub converter: Box<dyn Converter>,
+    /// Arguments of a named command, e.g. `Asia/Tokyo` for `;tz(Asia/Tokyo)`
+    pub args: Vec<String>,
+}
 
+impl ConversionStage {
+    fn new(stage: &CommandStage) -> Self {
+        let converter = get_custom_converter(&stage.name).unwrap_or_else(|| {
+            warn!("No converter registered for '{}'", stage.name);
+            Box::new(UnknownConverter {
+                name: stage.name.clone(),
+            })
+        });
+        Self {
+            converter,
+            args: stage.args.clone(),
+        }
+    }
+}
+
+/// Text converted by each stage in order, e.g. `;h|k`
 pub struct ConversionBlock {
     pub text: String,
-    pub converter: Box<dyn Converter>,
+    pub stages: Vec<ConversionStage>,
+}
+
+impl ConversionBlock {
+    fn new(text: String, stages: &[CommandStage]) -> Self {
+        trace!(
+            "Creating ConversionBlock - text: {}, stages: {:?}",
+            text,
+            stages
+        );
+        Self {
+            text,
+            stages: stages.iter().map(ConversionStage::new).collect(),
+        }
+    }
+
+    fn verbatim(text: String) -> Self {
+        trace!("Creating verbatim ConversionBlock - text: {}", text);
+        Self {
+            text,
+            stages: vec![ConversionStage {
+                converter: Box::new(NoneConverter),
+                args: Vec::new(),
+            }],
+        }
+    }
+
+    pub fn converter_names(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| stage.converter.name())
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// Result of converting one block
+pub struct ConvertedBlock {
+    pub original: String,
+    pub result: Result<String>,
+}
+
+impl ConvertedBlock {
+    /// The converted text, or the raw text when the block failed
+    pub fn text(&self) -> &str {
+        self.result.as_deref().unwrap_or(&self.original)
+    }
+}
+
+/// A converted message and the problems that were worked around, shown in
+/// the conversion log
+pub struct ConvertedText {
+    pub text: String,
+    pub warnings: Vec<String>,
 }
 
-pub struct Conversion;
+pub struct Conversion {
+    calculator: Mutex<CalculatorSession>,
+}
 
 impl Conversion {
     pub fn new() -> Self {
         info!("Creating new Conversion instance");
-        Self {}
+        Self {
+            calculator: Mutex::new(CalculatorSession::default()),
+        }
     }
 
-    pub fn convert_text(&self, text: &str) -> Result<String> {
+    /// Converts every block of `text`; malformed syntax and blocks that fail
+    /// keep their raw text and are reported as warnings instead of failing the
+    /// whole message
+    pub fn convert_text(&self, text: &str) -> ConvertedText {
         info!("Converting text: {}", text);
         trace!("Text length: {}", text.len());
-        let blocks = self.split_text(text)?;
+        let (blocks, syntax_errors) = self.split_text(text);
         trace!("Number of blocks after splitting: {}", blocks.len());
-        self.convert_blocks(blocks)
+        let converted_blocks = self.convert_blocks(&blocks);
+        let result = converted_blocks
+            .iter()
+            .map(ConvertedBlock::text)
+            .collect::<String>();
+        trace!("Final conversion result: {}", result);
+
+        let warnings = syntax_errors
+            .iter()
+            .map(ToString::to_string)
+            .chain(converted_blocks.iter().filter_map(|block| {
+                let e = block.result.as_ref().err()?;
+                Some(format!("{}: {:#}", block.original, e))
+            }))
+            .collect();
+        ConvertedText {
+            text: result,
+            warnings,
+        }
     }
 
-    pub fn convert_blocks(&self, blocks: Vec<ConversionBlock>) -> Result<String> {
+    pub fn convert_blocks(&self, blocks: &[ConversionBlock]) -> Vec<ConvertedBlock> {
         debug!("Converting blocks");
-        let mut result = String::new();
+        let config = self.get_config();
+        let mut converted_blocks: Vec<ConvertedBlock> = Vec::with_capacity(blocks.len());
         for (index, block) in blocks.iter().enumerate() {
             trace!("Processing block {}/{}", index + 1, blocks.len());
-            let converted = self.convert_block(&block)?;
-            debug!(
-                "Converted block - {}: {} -> {}",
-                block.converter.name(),
-                block.text,
-                converted
-            );
-            result.push_str(&converted);
-            trace!("Current result length: {}", result.len());
+            let context = ConversionContext {
+                config: &config,
+                index,
+                input: &block.text,
+                previous: converted_blocks.last().map(ConvertedBlock::text),
+                next: blocks.get(index + 1).map(|next| next.text.as_str()),
+                calculator: &self.calculator,
+            };
+            let result = self.convert_block(block, &context);
+            match &result {
+                Ok(converted) => debug!(
+                    "Converted block - {}: {} -> {}",
+                    block.converter_names(),
+                    block.text,
+                    converted
+                ),
+                Err(e) => error!(
+                    "Block {}/{} ({}) failed, keeping its text: {}: {:#}",
+                    index + 1,
+                    blocks.len(),
+                    block.converter_names(),
+                    block.text,
+                    e
+                ),
+            }
+            converted_blocks.push(ConvertedBlock {
+                original: block.text.clone(),
+                result,
+            });
         }
-        trace!("Final conversion result: {}", result);
-        Ok(result)
+        converted_blocks
     }
 
-    pub fn convert_block(&self, block: &ConversionBlock) -> Result<String> {
+    /// Feeds each stage's output into the next stage
+    pub fn convert_block(
+        &self,
+        block: &ConversionBlock,
+        context: &ConversionContext,
+    ) -> Result<String> {
         trace!("Converting block: {}", block.text);
-        trace!("Using converter: {}", block.converter.name());
+        trace!("Using converters: {}", block.converter_names());
         if block.text.is_empty() {
             trace!("Empty block, returning default string");
             return Ok(String::default());
         }
-        let result = block.converter.convert(&block.text);
+        let mut result = block.text.clone();
+        for (index, stage) in block.stages.iter().enumerate() {
+            result = stage
+                .converter
+                .convert_in_context(&result, &stage.args, context)
+                .map_err(|e| {
+                    e.context(format!(
+                        "Stage {}/{} ({}) failed",
+                        index + 1,
+                        block.stages.len(),
+                        stage.converter.name()
+                    ))
+                })?;
+            trace!("Stage {} result: {}", index + 1, result);
+        }
         trace!("Conversion result: {:?}", result);
-        result
+        Ok(result)
     }
 
-    pub fn split_text(&self, text: &str) -> Result<Vec<ConversionBlock>> {
+    /// Splits `text` into blocks and returns the malformed syntax that was
+    /// kept as text
+    pub fn split_text(&self, text: &str) -> (Vec<ConversionBlock>, Vec<SyntaxError>) {
         debug!("Splitting text: {}", text);
-        let mut text = text.to_string();
         let mut blocks = Vec::new();
-        let mut current_converter = 'r';
+        let mut current_stages = vec![CommandStage {
+            name: "r".to_string(),
+            args: Vec::new(),
+        }];
 
         let config = self.get_config();
         trace!(
@@ -71,44 +225,35 @@ impl Conversion {
             config.split
         );
 
-        if text.starts_with(&config.command) {
-            trace!("Text starts with command");
-            text = text.split_off(1);
-            if !text.is_empty() {
-                current_converter = text.chars().next().unwrap_or('n');
-                text = text.split_off(1);
-                trace!("Initial converter set to: {}", current_converter);
-            }
-        }
-
-        for (i, command_splitted) in text.split(&config.command).enumerate() {
-            trace!("Processing split {}", i);
-            let mut command_splitted = command_splitted.to_string();
-            if i != 0 {
-                if !command_splitted.is_empty() {
-                    current_converter = command_splitted.chars().next().unwrap_or('n');
-                    command_splitted = command_splitted.split_off(1);
-                    trace!("Converter changed to: {}", current_converter);
+        // Pre-conversion entries apply per token, so they never rewrite
+        // verbatim spans or command syntax, and their outputs become verbatim
+        // blocks that the converters pass through
+        let dictionary = self.get_dictionary();
+        let names = converter_command_names();
+        let (tokens, errors) = tokenize(text, &config.command, &config.split, &names);
+        for token in tokens {
+            match token {
+                Token::Command {
+                    stages,
+                    scope: Some(scope),
+                    position,
+                } => {
+                    trace!("Scoped converters {:?} (column {})", stages, position);
+                    blocks.extend(pre_conversion_blocks(&dictionary, &scope, &stages));
                 }
-            }
-
-            for splitted in command_splitted.split(&config.split) {
-                trace!(
-                    "Creating ConversionBlock - text: {}, converter: {}",
-                    splitted,
-                    current_converter
-                );
-                let converter = get_custom_converter(current_converter).unwrap_or_else(|| {
-                    warn!(
-                        "Failed to get custom converter for '{}', using default",
-                        current_converter
-                    );
-                    get_custom_converter('n').unwrap()
-                });
-                blocks.push(ConversionBlock {
-                    text: splitted.to_string(),
-                    converter,
-                });
+                Token::Command {
+                    stages,
+                    scope: None,
+                    position,
+                } => {
+                    trace!("Converters changed to: {:?} (column {})", stages, position);
+                    current_stages = stages;
+                }
+                Token::Split => trace!("Split"),
+                Token::Text(text) => {
+                    blocks.extend(pre_conversion_blocks(&dictionary, &text, &current_stages))
+                }
+                Token::Verbatim(text) => blocks.push(ConversionBlock::verbatim(text)),
             }
         }
 
@@ -117,7 +262,7 @@ impl Conversion {
             "Blocks: {:?}",
             blocks.iter().map(|b| &b.text).collect::<Vec<_>>()
         );
-        Ok(blocks)
+        (blocks, errors)
     }
 
     pub fn get_config(&self) -> Config {
@@ -126,4 +271,34 @@ impl Conversion {
         trace!("Config retrieved: {:?}", config);
         config
     }
+
+    pub fn get_dictionary(&self) -> Arc<CompiledDictionary> {
+        COMPILED_DICTIONARY.read().unwrap().clone()
+    }
+}
+
+fn pre_conversion_blocks(
+    dictionary: &CompiledDictionary,
+    text: &str,
+    stages: &[CommandStage],
+) -> Vec<ConversionBlock> {
+    let segments = dictionary
+        .matcher(DictionaryStage::PreConversion)
+        .segments(text);
+    if segments
+        .iter()
+        .any(|segment| matches!(segment, MatchedSegment::Replaced(_)))
+    {
+        info!(
+            "Dictionary conversion (PreConversion): {} -> {:?}",
+            text, segments
+        );
+    }
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            MatchedSegment::Text(text) => ConversionBlock::new(text, stages),
+            MatchedSegment::Replaced(text) => ConversionBlock::verbatim(text),
+        })
+        .collect()
 }
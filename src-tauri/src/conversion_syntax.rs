@@ -0,0 +1,470 @@
+use std::fmt;
+
+use tracing::{trace, warn};
+
+/// Escapes the next command, split, quote, pipe or backslash
+pub const ESCAPE: char = '\\';
+/// Text between two backticks is sent without conversion
+pub const VERBATIM_QUOTE: char = '`';
+/// Arguments of a named command, e.g. `;tz(Asia/Tokyo)`
+pub const ARGS_OPEN: char = '(';
+pub const ARGS_CLOSE: char = ')';
+/// Text converted by a named command only, e.g. `;kata{...}`
+pub const SCOPE_OPEN: char = '{';
+pub const SCOPE_CLOSE: char = '}';
+/// Chains converters within a command, e.g. `;h|k`
+pub const PIPE: char = '|';
+
+/// One converter of a command; `;h|tz(Asia/Tokyo)` has two stages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandStage {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Text(String),
+    Verbatim(String),
+    Command {
+        stages: Vec<CommandStage>,
+        /// Text of a `{...}` scope; the previous converter applies after it
+        scope: Option<String>,
+        position: usize,
+    },
+    Split,
+}
+
+/// `position` is the 1-based character column in the tokenized text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+pub struct Tokenizer {
+    chars: Vec<char>,
+    position: usize,
+    command: Vec<char>,
+    split: Vec<char>,
+    /// Single-letter triggers of registered converters
+    triggers: Vec<char>,
+    /// Multi-character command names, longest first
+    names: Vec<Vec<char>>,
+    tokens: Vec<Token>,
+    text: String,
+    /// Malformed syntax that was kept as text
+    errors: Vec<SyntaxError>,
+}
+
+impl Tokenizer {
+    /// `names` holds every trigger and command name; single characters are
+    /// treated as triggers
+    pub fn new(text: &str, command: &str, split: &str, names: &[String]) -> Self {
+        let (triggers, mut names): (Vec<_>, Vec<_>) = names
+            .iter()
+            .map(|name| name.chars().collect::<Vec<_>>())
+            .filter(|name| !name.is_empty())
+            .partition(|name| name.len() == 1);
+        let triggers = triggers.into_iter().map(|trigger| trigger[0]).collect();
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        Self {
+            chars: text.chars().collect(),
+            position: 0,
+            command: command.chars().collect(),
+            split: split.chars().collect(),
+            triggers,
+            names,
+            tokens: Vec::new(),
+            text: String::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Malformed syntax such as an unterminated `{` is kept as text and
+    /// returned as an error, so a typo never discards the rest of the message
+    pub fn tokenize(mut self) -> (Vec<Token>, Vec<SyntaxError>) {
+        while self.position < self.chars.len() {
+            let c = self.chars[self.position];
+            if c == ESCAPE {
+                self.read_escape();
+            } else if c == VERBATIM_QUOTE {
+                if let Err(e) = self.read_verbatim() {
+                    warn!("{}, keeping it as text", e);
+                    self.errors.push(e);
+                    self.text.push(c);
+                    self.position += 1;
+                }
+            } else if self.at(&self.command) && self.stage_at(self.position + self.command.len()) {
+                let (position, text, token_count) =
+                    (self.position, self.text.clone(), self.tokens.len());
+                if let Err(e) = self.read_command() {
+                    warn!("{}, keeping it as text", e);
+                    self.errors.push(e);
+                    self.tokens.truncate(token_count);
+                    self.text = text;
+                    self.text.extend(self.command.iter());
+                    self.position = position + self.command.len();
+                }
+            } else if self.at(&self.split) {
+                self.flush_text();
+                self.tokens.push(Token::Split);
+                self.position += self.split.len();
+            } else {
+                self.text.push(c);
+                self.position += 1;
+            }
+        }
+        self.flush_text();
+        trace!("Tokens: {:?}", self.tokens);
+        (self.tokens, self.errors)
+    }
+
+    fn at(&self, pattern: &[char]) -> bool {
+        self.at_position(self.position, pattern)
+    }
+
+    fn at_position(&self, position: usize, pattern: &[char]) -> bool {
+        !pattern.is_empty()
+            && self
+                .chars
+                .get(position..)
+                .is_some_and(|rest| rest.starts_with(pattern))
+    }
+
+    /// A command or pipe followed by anything but a letter or a name, such
+    /// as `hello;` or `;)`, is kept as literal text; an ASCII letter that is
+    /// not a trigger still starts a command, so a mistyped trigger shows the
+    /// unknown converter warning instead of being sent as is
+    fn stage_at(&self, position: usize) -> bool {
+        self.chars
+            .get(position)
+            .is_some_and(|c| self.triggers.contains(c) || c.is_ascii_alphabetic())
+            || self.name_at(position).is_some()
+    }
+
+    fn flush_text(&mut self) {
+        if !self.text.is_empty() {
+            self.tokens
+                .push(Token::Text(std::mem::take(&mut self.text)));
+        }
+    }
+
+    /// A backslash that does not precede a special sequence is kept as is
+    fn read_escape(&mut self) {
+        self.position += 1;
+        let escaped = [
+            self.command.clone(),
+            self.split.clone(),
+            vec![VERBATIM_QUOTE],
+            vec![PIPE],
+            vec![ESCAPE],
+        ]
+        .into_iter()
+        .find(|pattern| self.at(pattern));
+        match escaped {
+            Some(pattern) => {
+                self.text.extend(pattern.iter());
+                self.position += pattern.len();
+            }
+            None => self.text.push(ESCAPE),
+        }
+    }
+
+    fn read_verbatim(&mut self) -> Result<(), SyntaxError> {
+        let start = self.position;
+        let end = self.chars[start + 1..]
+            .iter()
+            .position(|&c| c == VERBATIM_QUOTE)
+            .map(|offset| start + 1 + offset)
+            .ok_or_else(|| SyntaxError {
+                position: start + 1,
+                message: format!("Unterminated '{}'", VERBATIM_QUOTE),
+            })?;
+        self.flush_text();
+        self.tokens
+            .push(Token::Verbatim(self.chars[start + 1..end].iter().collect()));
+        self.position = end + 1;
+        Ok(())
+    }
+
+    /// Reads `;x`, or `;name(args)` and `;name{text}`, optionally
+    /// chained with `|` such as `;h|kata{text}`
+    ///
+    /// A name is only recognized when it is followed by arguments, a scope or
+    /// a pipe, so text after a trigger keeps its meaning: `;kanji desu` is
+    /// `;k` followed by `anji desu`, and `;kata() desu` or `;kata{desu}` use
+    /// the name.
+    fn read_command(&mut self) -> Result<(), SyntaxError> {
+        let command_position = self.position + 1;
+        self.position += self.command.len();
+        self.flush_text();
+
+        let mut stages = vec![self.read_stage()?];
+        while self.chars.get(self.position) == Some(&PIPE) && self.stage_at(self.position + 1) {
+            self.position += 1;
+            stages.push(self.read_stage()?);
+        }
+
+        let scope = if self.chars.get(self.position) == Some(&SCOPE_OPEN) {
+            Some(self.read_enclosed(SCOPE_OPEN, SCOPE_CLOSE)?)
+        } else {
+            let named = stages
+                .last()
+                .is_some_and(|stage| stage.name.chars().count() > 1);
+            if named
+                && self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_whitespace())
+            {
+                self.position += 1;
+            }
+            None
+        };
+        self.tokens.push(Token::Command {
+            stages,
+            scope,
+            position: command_position,
+        });
+        Ok(())
+    }
+
+    /// Reads a single-letter trigger or a name with optional arguments; the
+    /// caller checks `stage_at` first
+    fn read_stage(&mut self) -> Result<CommandStage, SyntaxError> {
+        let Some(name) = self.name_at(self.position) else {
+            let trigger = self.chars[self.position];
+            self.position += 1;
+            return Ok(CommandStage {
+                name: trigger.to_string(),
+                args: Vec::new(),
+            });
+        };
+        self.position += name.chars().count();
+
+        let args = if self.chars.get(self.position) == Some(&ARGS_OPEN) {
+            self.read_enclosed(ARGS_OPEN, ARGS_CLOSE)?
+                .split(',')
+                .map(|arg| arg.trim().to_string())
+                .filter(|arg| !arg.is_empty())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(CommandStage { name, args })
+    }
+
+    fn name_at(&self, position: usize) -> Option<String> {
+        self.names.iter().find_map(|name| {
+            if !self.at_position(position, name) {
+                return None;
+            }
+            let followed_by_delimiter = self
+                .chars
+                .get(position + name.len())
+                .is_some_and(|&c| c == ARGS_OPEN || c == SCOPE_OPEN || c == PIPE);
+            followed_by_delimiter.then(|| name.iter().collect())
+        })
+    }
+
+    /// Reads up to `close`; a backslash escapes `close` and itself
+    fn read_enclosed(&mut self, open: char, close: char) -> Result<String, SyntaxError> {
+        let start = self.position;
+        let mut content = String::new();
+        self.position += 1;
+        while let Some(&c) = self.chars.get(self.position) {
+            let next = self.chars.get(self.position + 1).copied();
+            if c == close {
+                self.position += 1;
+                return Ok(content);
+            } else if c == ESCAPE && (next == Some(close) || next == Some(ESCAPE)) {
+                content.extend(next);
+                self.position += 2;
+            } else {
+                content.push(c);
+                self.position += 1;
+            }
+        }
+        Err(SyntaxError {
+            position: start + 1,
+            message: format!("Unterminated '{}'", open),
+        })
+    }
+}
+
+pub fn tokenize(
+    text: &str,
+    command: &str,
+    split: &str,
+    names: &[String],
+) -> (Vec<Token>, Vec<SyntaxError>) {
+    Tokenizer::new(text, command, split, names).tokenize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["r", "h", "k", "kata", "tz"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn tokens(text: &str) -> Vec<Token> {
+        tokenize_with(text, ";", "/")
+    }
+
+    fn tokenize_with(text: &str, command: &str, split: &str) -> Vec<Token> {
+        let (tokens, errors) = tokenize(text, command, split, &names());
+        assert!(errors.is_empty(), "{:?}", errors);
+        tokens
+    }
+
+    fn command(names: &[&str], scope: Option<&str>, position: usize) -> Token {
+        Token::Command {
+            stages: names
+                .iter()
+                .map(|name| CommandStage {
+                    name: name.to_string(),
+                    args: Vec::new(),
+                })
+                .collect(),
+            scope: scope.map(str::to_string),
+            position,
+        }
+    }
+
+    fn text(text: &str) -> Token {
+        Token::Text(text.to_string())
+    }
+
+    #[test]
+    fn reads_commands_and_splits() {
+        assert_eq!(
+            tokens(";h|k abc/def"),
+            vec![
+                command(&["h", "k"], None, 1),
+                text(" abc"),
+                Token::Split,
+                text("def"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_arguments_and_scopes() {
+        assert_eq!(
+            tokens("a;tz(Asia/Tokyo, 12h) 9;kata{b}c"),
+            vec![
+                text("a"),
+                Token::Command {
+                    stages: vec![CommandStage {
+                        name: "tz".to_string(),
+                        args: vec!["Asia/Tokyo".to_string(), "12h".to_string()],
+                    }],
+                    scope: None,
+                    position: 2,
+                },
+                text("9"),
+                command(&["kata"], Some("b"), 24),
+                text("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_special_sequences() {
+        assert_eq!(tokens(r"a\;h\/b\`c\|d\\e\x"), vec![text(r"a;h/b`c|d\e\x")]);
+        assert_eq!(
+            tokens(r";kata{a\}b\\}c"),
+            vec![command(&["kata"], Some(r"a}b\"), 1), text("c")]
+        );
+    }
+
+    #[test]
+    fn keeps_verbatim_spans() {
+        assert_eq!(
+            tokens("a`;h/b`c"),
+            vec![text("a"), Token::Verbatim(";h/b".to_string()), text("c")]
+        );
+    }
+
+    #[test]
+    fn keeps_stray_command_characters() {
+        assert_eq!(tokens("hello; ;) ;1 ;あ"), vec![text("hello; ;) ;1 ;あ")]);
+        assert_eq!(
+            tokens(";kanji"),
+            vec![command(&["k"], None, 1), text("anji")]
+        );
+    }
+
+    #[test]
+    fn reads_unregistered_triggers() {
+        assert_eq!(
+            tokens(";x abc;h|q d"),
+            vec![
+                command(&["x"], None, 1),
+                text(" abc"),
+                command(&["h", "q"], None, 7),
+                text(" d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn supports_multibyte_command_and_split() {
+        assert_eq!(
+            tokenize_with("あ；h|kいう／え", "；", "／"),
+            vec![
+                text("あ"),
+                command(&["h", "k"], None, 2),
+                text("いう"),
+                Token::Split,
+                text("え"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_unterminated_spans_as_text() {
+        for (input, expected, message, position) in [
+            ("ab`cd", "ab`cd", "Unterminated '`'", 3),
+            ("あ;kata{いう", "あ;kata{いう", "Unterminated '{'", 7),
+            (";tz(Asia x", ";tz(Asia x", "Unterminated '('", 4),
+        ] {
+            let (tokens, errors) = tokenize(input, ";", "/", &names());
+            assert_eq!(tokens, vec![text(expected)], "{}", input);
+            assert_eq!(
+                errors,
+                vec![SyntaxError {
+                    position,
+                    message: message.to_string(),
+                }],
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn continues_after_unterminated_span() {
+        let (tokens, errors) = tokenize("a;tz(x ;k b", ";", "/", &names());
+        assert_eq!(
+            tokens,
+            vec![text("a;tz(x "), command(&["k"], None, 8), text(" b")]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "Unterminated '(' at column 5");
+    }
+}
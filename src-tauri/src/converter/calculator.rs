@@ -1,27 +1,373 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
 use calc::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use tracing::{debug, info, trace};
 
-use super::converter::Converter;
+use super::{
+    converter::{ConversionContext, Converter, ConverterRegistration},
+    units::{self, UnitExpression},
+};
+
+const ANS: &str = "ans";
+/// Functions and constants evaluated by `calc` itself
+const BUILTINS: &[&str] = &[
+    "abs", "ceil", "floor", "round", "sin", "cos", "tan", "sinh", "cosh", "tanh", "asin", "acos",
+    "atan", "asinh", "acosh", "atanh", "rad", "deg", "sqrt", "cbrt", "log", "lg", "ln", "exp",
+    "pi", "e",
+];
+const DEFAULT_PRECISION: usize = 10;
+/// Unit conversions are rounded for the chatbox unless a precision is given
+const UNIT_PRECISION: usize = 2;
+/// Significant digits kept for unit results smaller than 1
+const UNIT_SIGNIFICANT_DIGITS: usize = 3;
+/// Digits beyond this are `f64` noise such as `0.30000000000000004`
+const SIGNIFICANT_DIGITS: usize = 15;
+
+static ASSIGNMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*=(.*)$").unwrap());
+
+/// Calculator state owned by a `Conversion` and shared by its blocks through
+/// `ConversionContext::calculator`
+#[derive(Debug, Default)]
+pub struct CalculatorSession {
+    /// Named variables and `ans`
+    variables: HashMap<String, f64>,
+}
+
+impl CalculatorSession {
+    /// Evaluates `expr` or `name = expr`, updating `ans` and the variable
+    pub fn evaluate(&mut self, text: &str) -> Result<(Option<String>, f64)> {
+        let (name, expr) = match ASSIGNMENT.captures(text) {
+            Some(caps) => (Some(caps[1].to_string()), caps[2].to_string()),
+            None => (None, text.to_string()),
+        };
+        if let Some(name) = name
+            .as_deref()
+            .filter(|name| *name == ANS || BUILTINS.contains(name))
+        {
+            return Err(anyhow!("'{}' cannot be assigned", name));
+        }
+
+        let substituted = self.substitute(&expr)?;
+        trace!("Substituted expression: {}", substituted);
+        let value = Context::<f64>::default()
+            .evaluate(&substituted)
+            .map_err(|e| anyhow!("{}", e))?;
+
+        self.variables.insert(ANS.to_string(), value);
+        if let Some(name) = &name {
+            debug!("Variable {} set to {}", name, value);
+            self.variables.insert(name.clone(), value);
+        }
+        Ok((name, value))
+    }
+
+    fn set_ans(&mut self, value: f64) {
+        self.variables.insert(ANS.to_string(), value);
+    }
+
+    /// Replaces variables with their values; `0xff` and built-ins such as
+    /// `sqrt` and `pi` are left to `calc`
+    fn substitute(&self, expr: &str) -> Result<String> {
+        let mut result = String::with_capacity(expr.len());
+        let mut chars = expr.char_indices().peekable();
+        let mut previous = None;
+        while let Some((start, c)) = chars.next() {
+            let starts_identifier = (c.is_ascii_alphabetic() || c == '_')
+                && !previous.is_some_and(|p: char| p.is_ascii_alphanumeric() || p == '.');
+            if !starts_identifier {
+                result.push(c);
+                previous = Some(c);
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some(&(index, next)) = chars.peek() {
+                if !(next.is_ascii_alphanumeric() || next == '_') {
+                    break;
+                }
+                end = index + next.len_utf8();
+                chars.next();
+            }
+            let name = &expr[start..end];
+            previous = name.chars().last();
+            if BUILTINS.contains(&name) {
+                result.push_str(name);
+                continue;
+            }
+            let value = self.variables.get(name).ok_or_else(|| match name {
+                ANS => anyhow!("'{}' is not set yet", ANS),
+                _ => anyhow!("Unknown variable '{}'", name),
+            })?;
+            result.push_str(&format!("({})", value));
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Decimal,
+    Integer,
+    Hex,
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFormat {
+    pub mode: OutputMode,
+    /// Separates thousands with `,`, or hex and binary digits with `_` every 4
+    pub grouping: bool,
+    /// Maximum number of decimal places; trailing zeros are removed
+    pub precision: usize,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self {
+            mode: OutputMode::default(),
+            grouping: false,
+            precision: DEFAULT_PRECISION,
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Parses arguments such as `;calc(hex)` or `;calc(3, group)`
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        Self::default().with_args(args)
+    }
+
+    pub fn with_args(self, args: &[String]) -> Result<Self> {
+        let mut format = self;
+        for arg in args {
+            match arg.to_lowercase().as_str() {
+                "dec" | "decimal" => format.mode = OutputMode::Decimal,
+                "int" | "integer" => format.mode = OutputMode::Integer,
+                "hex" => format.mode = OutputMode::Hex,
+                "bin" | "binary" => format.mode = OutputMode::Binary,
+                "group" | "," => format.grouping = true,
+                other => {
+                    format.precision = other
+                        .parse()
+                        .map_err(|_| anyhow!("Unknown calculator option '{}'", arg))?
+                }
+            }
+        }
+        Ok(format)
+    }
+
+    pub fn format(&self, value: f64) -> Result<String> {
+        if !value.is_finite() {
+            return Ok(value.to_string());
+        }
+        let formatted = match self.mode {
+            OutputMode::Decimal => {
+                let integer_digits = value.abs().trunc().to_string().len();
+                let precision = self
+                    .precision
+                    .min(SIGNIFICANT_DIGITS.saturating_sub(integer_digits));
+                let fixed = trim_fraction(&format!("{:.*}", precision, value)).to_string();
+                match fixed.as_str() {
+                    // Too small for the precision, e.g. `1e-12`
+                    "0" | "-0" if value != 0.0 => format_scientific(value, self.precision),
+                    "-0" => "0".to_string(),
+                    _ if self.grouping => group_decimal(&fixed),
+                    _ => fixed,
+                }
+            }
+            OutputMode::Integer => {
+                let integer = to_integer(value)?.to_string();
+                if self.grouping {
+                    group_decimal(&integer)
+                } else {
+                    integer
+                }
+            }
+            OutputMode::Hex | OutputMode::Binary => {
+                let integer = to_integer(value)?;
+                let (prefix, digits) = match self.mode {
+                    OutputMode::Hex => ("0x", format!("{:X}", integer.unsigned_abs())),
+                    _ => ("0b", format!("{:b}", integer.unsigned_abs())),
+                };
+                let digits = if self.grouping {
+                    group_digits(&digits, 4, '_')
+                } else {
+                    digits
+                };
+                let sign = if integer < 0 { "-" } else { "" };
+                format!("{}{}{}", sign, prefix, digits)
+            }
+        };
+        Ok(formatted)
+    }
+}
+
+/// `1.500` -> `1.5`, `2.0` -> `2`
+fn trim_fraction(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
+
+/// `0.0000000000015` -> `1.5e-12`
+fn format_scientific(value: f64, precision: usize) -> String {
+    let exponent = value.abs().log10().floor() as i32;
+    let mantissa = value / 10f64.powi(exponent);
+    let precision = precision.min(SIGNIFICANT_DIGITS - 1);
+    let mantissa = format!("{:.*}", precision, mantissa);
+    format!("{}e{}", trim_fraction(&mantissa), exponent)
+}
+
+fn to_integer(value: f64) -> Result<i64> {
+    let rounded = value.round();
+    if rounded < i64::MIN as f64 || rounded >= i64::MAX as f64 {
+        return Err(anyhow!("{:e} is out of the integer range", value));
+    }
+    Ok(rounded as i64)
+}
+
+fn group_digits(digits: &str, size: usize, separator: char) -> String {
+    let len = digits.chars().count();
+    let mut grouped = String::with_capacity(digits.len() + len / size);
+    for (index, c) in digits.chars().enumerate() {
+        if index > 0 && (len - index).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// `-1234567.89` -> `-1,234,567.89`
+fn group_decimal(number: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let mut grouped = format!("{}{}", sign, group_digits(integer, 3, ','));
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
 
 pub struct CalculatorConverter;
 
-impl Converter for CalculatorConverter {
-    fn convert(&self, text: &str) -> anyhow::Result<String> {
-        debug!("Evaluating expression: {}", text);
-        let mut ctx = Context::<f64>::default();
-        let result = match ctx.evaluate(text) {
-            Ok(result) => {
-                let formatted = format!("{} = {}", text, result.to_string());
+impl CalculatorConverter {
+    /// Returns `expr = result`, or `expr = error: ...` if evaluation fails
+    fn evaluate(
+        &self,
+        text: &str,
+        args: &[String],
+        session: &mut CalculatorSession,
+    ) -> Result<String> {
+        let expr = text.trim();
+        debug!("Evaluating expression: {}", expr);
+        let expression = units::parse(expr);
+        let format = match expression {
+            Some(_) => OutputFormat {
+                precision: UNIT_PRECISION,
+                ..OutputFormat::default()
+            },
+            None => OutputFormat::default(),
+        }
+        .with_args(args)?;
+        trace!("Calculator output format: {:?}", format);
+
+        let result = match &expression {
+            Some(expression) => self.evaluate_units(expression, &format, session),
+            None => self.evaluate_expression(expr, &format, session),
+        };
+        match result {
+            Ok(formatted) => {
                 info!("Evaluation successful: {}", formatted);
-                formatted
+                Ok(formatted)
             }
             Err(e) => {
                 debug!("Evaluation failed: {}", e);
-                e.to_string()
+                Ok(format!("{} = error: {}", expr, e))
             }
-        };
+        }
+    }
 
-        Ok(result)
+    fn evaluate_expression(
+        &self,
+        expr: &str,
+        format: &OutputFormat,
+        session: &mut CalculatorSession,
+    ) -> Result<String> {
+        let (name, value) = session.evaluate(expr)?;
+        Ok(format!(
+            "{} = {}",
+            name.as_deref().unwrap_or(expr),
+            format.format(value)?
+        ))
+    }
+
+    /// Returns `5 km = 3.11 mi`; the amount may use variables and `ans`
+    fn evaluate_units(
+        &self,
+        expression: &UnitExpression,
+        format: &OutputFormat,
+        session: &mut CalculatorSession,
+    ) -> Result<String> {
+        let (_, amount) = session.evaluate(&expression.amount)?;
+        let conversion = units::convert(amount, &expression.from, &expression.to)?;
+        session.set_ans(conversion.value);
+
+        let mut value_format = format.clone();
+        let magnitude = conversion.value.abs();
+        if magnitude > 0.0 && magnitude < 1.0 {
+            let leading_zeros = (-magnitude.log10()).floor() as usize;
+            value_format.precision = value_format
+                .precision
+                .max(leading_zeros + UNIT_SIGNIFICANT_DIGITS);
+        }
+        let mut formatted = format!(
+            "{} {} = {} {}",
+            OutputFormat::default().format(amount)?,
+            conversion.from,
+            value_format.format(conversion.value)?,
+            conversion.to
+        );
+        if let Some(note) = conversion.note {
+            formatted.push_str(&format!(" ({})", note));
+        }
+        Ok(formatted)
+    }
+}
+
+impl Converter for CalculatorConverter {
+    /// Evaluates without variables, e.g. for dictionary entries
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        self.convert_with_args(text, &[])
+    }
+
+    /// `;calc(hex)`, `;calc(int, group)` or `;calc(3)` for 3 decimal places
+    fn convert_with_args(&self, text: &str, args: &[String]) -> anyhow::Result<String> {
+        self.evaluate(text, args, &mut CalculatorSession::default())
+    }
+
+    /// Uses the variables and `ans` of the conversion session
+    fn convert_in_context(
+        &self,
+        text: &str,
+        args: &[String],
+        context: &ConversionContext,
+    ) -> anyhow::Result<String> {
+        self.evaluate(text, args, &mut context.calculator.lock().unwrap())
     }
 
     fn name(&self) -> String {
@@ -29,3 +375,18 @@ impl Converter for CalculatorConverter {
         "calculator".to_string()
     }
 }
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "計算",
+        "数式や単位・通貨を計算します。ans や x=3 のような変数は次の計算でも使えます",
+        || Box::new(CalculatorConverter),
+    )
+    .trigger('c')
+    .alias("calc")
+    .example("1+2*3", "1+2*3 = 7")
+    .example("0.1+0.2", "0.1+0.2 = 0.3")
+    .example("x=1500*3", "x = 4500")
+    .example("5 km in mi", "5 km = 3.11 mi")
+    .example("30C in F", "30 °C = 86 °F")
+}
@@ -1,29 +1,234 @@
-use anyhow::Result;
-use tracing::{debug, trace};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tracing::{debug, trace, warn};
+
+use crate::config::Config;
 
 use super::{
-    calculator::CalculatorConverter, hiragana::HiraganaConverter, katakana::KatakanaConverter,
-    none_converter::NoneConverter, roman_to_kanji::RomanToKanjiConverter,
+    calculator, dice, furigana, hangul, hiragana, kana, kansuji, katakana, none_converter,
+    roman_to_kanji, text_style, timezone, width,
 };
 
+/// What a converter can see besides the text of its own block
+pub struct ConversionContext<'a> {
+    pub config: &'a Config,
+    /// Position of the block in the message, starting at 0
+    pub index: usize,
+    /// Raw text of the block, before its first stage
+    pub input: &'a str,
+    /// Converted text of the preceding block
+    pub previous: Option<&'a str>,
+    /// Raw text of the following block
+    pub next: Option<&'a str>,
+    /// Calculator variables and `ans`, kept until the handler is recreated
+    pub calculator: &'a Mutex<calculator::CalculatorSession>,
+}
+
 pub trait Converter {
     fn convert(&self, text: &str) -> Result<String>;
     fn name(&self) -> String;
+
+    /// Called with the arguments of a named command such as `;tz(Asia/Tokyo)`
+    fn convert_with_args(&self, text: &str, args: &[String]) -> Result<String> {
+        if !args.is_empty() {
+            return Err(anyhow!(
+                "Converter '{}' does not take arguments",
+                self.name()
+            ));
+        }
+        self.convert(text)
+    }
+
+    /// Called by the conversion pipeline; override to use the surrounding blocks,
+    /// the config or the calculator session
+    fn convert_in_context(
+        &self,
+        text: &str,
+        args: &[String],
+        _context: &ConversionContext,
+    ) -> Result<String> {
+        self.convert_with_args(text, args)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConverterExample {
+    pub input: String,
+    pub output: String,
+}
+
+/// Converter metadata shown in the Dictionary tab and the converter list
+#[derive(Debug, Clone, Serialize)]
+pub struct ConverterInfo {
+    /// Single-letter command, e.g. `r` for `;r`
+    pub trigger: Option<char>,
+    pub name: String,
+    /// Additional command names, e.g. `kata` for `;kata{...}`
+    pub aliases: Vec<String>,
+    pub display_name: String,
+    pub description: String,
+    pub examples: Vec<ConverterExample>,
+}
+
+pub struct ConverterRegistration {
+    pub info: ConverterInfo,
+    factory: fn() -> Box<dyn Converter>,
+}
+
+impl ConverterRegistration {
+    pub fn new(display_name: &str, description: &str, factory: fn() -> Box<dyn Converter>) -> Self {
+        Self {
+            info: ConverterInfo {
+                trigger: None,
+                name: factory().name(),
+                aliases: Vec::new(),
+                display_name: display_name.to_string(),
+                description: description.to_string(),
+                examples: Vec::new(),
+            },
+            factory,
+        }
+    }
+
+    pub fn trigger(mut self, trigger: char) -> Self {
+        self.info.trigger = Some(trigger);
+        self
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.info.aliases.push(alias.to_string());
+        self
+    }
+
+    /// Returns true if `name` is the trigger, the converter name or an alias
+    fn matches(&self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let is_trigger = match (chars.next(), chars.next()) {
+            (Some(c), None) => self.info.trigger == Some(c),
+            _ => false,
+        };
+        is_trigger || self.info.name == name || self.info.aliases.iter().any(|a| a == name)
+    }
+
+    fn command_names(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.info.name).chain(self.info.aliases.iter())
+    }
+
+    pub fn example(mut self, input: &str, output: &str) -> Self {
+        self.info.examples.push(ConverterExample {
+            input: input.to_string(),
+            output: output.to_string(),
+        });
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct ConverterRegistry {
+    registrations: Vec<ConverterRegistration>,
+}
+
+impl ConverterRegistry {
+    pub fn register(&mut self, registration: ConverterRegistration) {
+        let conflicts = self
+            .registrations
+            .iter()
+            .filter(|r| {
+                registration
+                    .info
+                    .trigger
+                    .is_some_and(|t| r.info.trigger == Some(t))
+                    || registration.command_names().any(|name| r.matches(name))
+            })
+            .map(|r| r.info.name.clone())
+            .collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            warn!(
+                "Converter {} conflicts with {:?}, replacing them",
+                registration.info.name, conflicts
+            );
+            self.registrations
+                .retain(|r| !conflicts.contains(&r.info.name));
+        }
+        trace!(
+            "Registering converter {} ({:?}, {:?})",
+            registration.info.name,
+            registration.info.trigger,
+            registration.info.aliases
+        );
+        self.registrations.push(registration);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Box<dyn Converter>> {
+        self.registrations
+            .iter()
+            .find(|r| r.matches(name))
+            .map(|r| (r.factory)())
+    }
+
+    pub fn list(&self) -> Vec<ConverterInfo> {
+        self.registrations.iter().map(|r| r.info.clone()).collect()
+    }
+
+    /// Every trigger, name and alias, used by the block tokenizer
+    pub fn command_names(&self) -> Vec<String> {
+        self.registrations
+            .iter()
+            .flat_map(|r| {
+                r.info
+                    .trigger
+                    .map(String::from)
+                    .into_iter()
+                    .chain(r.command_names().cloned())
+            })
+            .collect()
+    }
 }
 
-pub fn get_custom_converter(prefix: char) -> Option<Box<dyn Converter>> {
-    debug!("Getting custom converter for prefix: {}", prefix);
-    let converter = match prefix {
-        'r' => Some(Box::new(RomanToKanjiConverter) as Box<dyn Converter>),
-        'h' => Some(Box::new(HiraganaConverter) as Box<dyn Converter>),
-        'c' => Some(Box::new(CalculatorConverter) as Box<dyn Converter>),
-        'n' => Some(Box::new(NoneConverter) as Box<dyn Converter>),
-        'k' => Some(Box::new(KatakanaConverter) as Box<dyn Converter>),
-        _ => None,
-    };
+static REGISTRY: Lazy<ConverterRegistry> = Lazy::new(|| {
+    let mut registry = ConverterRegistry::default();
+    registry.register(roman_to_kanji::registration());
+    registry.register(furigana::registration());
+    registry.register(hiragana::registration());
+    registry.register(katakana::registration());
+    registry.register(kana::hiragana_registration());
+    registry.register(kana::katakana_registration());
+    registry.register(hangul::registration());
+    registry.register(width::zenkaku_registration());
+    registry.register(width::hankaku_registration());
+    for registration in text_style::registrations() {
+        registry.register(registration);
+    }
+    registry.register(calculator::registration());
+    registry.register(dice::registration());
+    registry.register(timezone::registration());
+    registry.register(kansuji::registration());
+    registry.register(none_converter::registration());
+    debug!(
+        "Converter registry initialized with {} converters",
+        registry.registrations.len()
+    );
+    registry
+});
+
+/// Looks up a converter by its single-letter trigger, name or alias
+pub fn get_custom_converter(name: &str) -> Option<Box<dyn Converter>> {
+    debug!("Getting custom converter for: {}", name);
+    let converter = REGISTRY.get(name);
     match &converter {
         Some(c) => debug!("Custom converter found: {}", c.name()),
-        None => trace!("No custom converter found for prefix: {}", prefix),
+        None => trace!("No custom converter found for: {}", name),
     }
     converter
 }
+
+pub fn list_converters() -> Vec<ConverterInfo> {
+    REGISTRY.list()
+}
+
+pub fn converter_command_names() -> Vec<String> {
+    REGISTRY.command_names()
+}
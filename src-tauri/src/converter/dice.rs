@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use regex::Regex;
+use tracing::{debug, trace};
+
+use super::converter::{Converter, ConverterRegistration};
+
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u32 = 1_000_000;
+const DEFAULT_ROLL: &str = "1d6";
+const COIN: [&str; 2] = ["表", "裏"];
+
+/// `2d6`, `d100`, `4d6kh3` or a constant, with the sign that precedes it
+static TERM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*([+-])?\s*(?:(\d*)d(\d+)(?:(kh|kl|dh|dl)(\d+))?|(\d+))\s*").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keep {
+    Highest(usize),
+    Lowest(usize),
+}
+
+/// Rolls `count` dice and marks the ones dropped by `keep` with parentheses
+fn roll_dice(rng: &mut impl Rng, count: u32, sides: u32, keep: Option<Keep>) -> (String, i64) {
+    let rolls = (0..count)
+        .map(|_| rng.gen_range(1..=sides))
+        .collect::<Vec<_>>();
+
+    let mut order = (0..rolls.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| rolls[index]);
+    let kept = match keep {
+        None => order,
+        Some(Keep::Highest(n)) => order[order.len() - n..].to_vec(),
+        Some(Keep::Lowest(n)) => order[..n].to_vec(),
+    };
+
+    let shown = rolls
+        .iter()
+        .enumerate()
+        .map(|(index, roll)| {
+            if kept.contains(&index) {
+                roll.to_string()
+            } else {
+                format!("({})", roll)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let total = kept.iter().map(|&index| rolls[index] as i64).sum();
+    (format!("[{}]", shown), total)
+}
+
+/// `2d6+3` -> `[4,2]+3 = 9`
+pub fn roll(expr: &str, rng: &mut impl Rng) -> Result<String> {
+    let mut rest = expr;
+    let mut shown = String::new();
+    let mut total: i64 = 0;
+    while !rest.trim().is_empty() {
+        let caps = TERM
+            .captures(rest)
+            .ok_or_else(|| anyhow!("Invalid dice notation '{}'", rest.trim()))?;
+        let negative = caps.get(1).is_some_and(|sign| sign.as_str() == "-");
+        if caps.get(1).is_none() && !shown.is_empty() {
+            return Err(anyhow!("Expected '+' or '-' before '{}'", rest.trim()));
+        }
+
+        let (term, value) = match caps.get(6) {
+            Some(constant) => {
+                let value = constant
+                    .as_str()
+                    .parse::<i64>()
+                    .map_err(|e| anyhow!("Invalid number '{}': {}", constant.as_str(), e))?;
+                (value.to_string(), value)
+            }
+            None => {
+                // Numbers too large to parse are out of range like any other
+                let count = match &caps[2] {
+                    "" => Some(1),
+                    count => count.parse::<u32>().ok(),
+                }
+                .filter(|count| (1..=MAX_DICE).contains(count))
+                .ok_or_else(|| anyhow!("Dice count must be between 1 and {}", MAX_DICE))?;
+                let sides = caps[3]
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|sides| (1..=MAX_SIDES).contains(sides))
+                    .ok_or_else(|| anyhow!("Dice sides must be between 1 and {}", MAX_SIDES))?;
+                let keep = match (caps.get(4), caps.get(5)) {
+                    (Some(mode), Some(n)) => {
+                        let n = n
+                            .as_str()
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&n| n <= count as usize)
+                            .ok_or_else(|| {
+                                anyhow!("Cannot keep or drop {} of {} dice", n.as_str(), count)
+                            })?;
+                        let dropped = count as usize - n;
+                        Some(match mode.as_str().to_lowercase().as_str() {
+                            "kh" => Keep::Highest(n),
+                            "kl" => Keep::Lowest(n),
+                            "dh" => Keep::Lowest(dropped),
+                            _ => Keep::Highest(dropped),
+                        })
+                    }
+                    _ => None,
+                };
+                roll_dice(rng, count, sides, keep)
+            }
+        };
+        trace!("Dice term: {} = {}", term, value);
+
+        let sum = if negative {
+            shown.push('-');
+            total.checked_sub(value)
+        } else {
+            if !shown.is_empty() {
+                shown.push('+');
+            }
+            total.checked_add(value)
+        };
+        total = sum.ok_or_else(|| anyhow!("The total of '{}' is too large", expr.trim()))?;
+        shown.push_str(&term);
+        rest = &rest[caps.get(0).map_or(0, |m| m.end())..];
+    }
+    Ok(format!("{} = {}", shown, total))
+}
+
+/// Picks one item of a list separated by `,` or `、`
+pub fn pick(list: &str, rng: &mut impl Rng) -> Result<String> {
+    let items = list
+        .split([',', '、'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect::<Vec<_>>();
+    items
+        .choose(rng)
+        .map(|item| item.to_string())
+        .ok_or_else(|| anyhow!("Nothing to pick from '{}'", list))
+}
+
+/// Echoes the text with the result, e.g. `2d6+3 → [4,2]+3 = 9`
+pub fn evaluate(text: &str, rng: &mut impl Rng) -> Result<String> {
+    let expr = match text.trim() {
+        "" => DEFAULT_ROLL,
+        expr => expr,
+    };
+    let result = match expr.to_lowercase().as_str() {
+        "coin" | "コイン" => COIN.choose(rng).unwrap_or(&COIN[0]).to_string(),
+        _ if expr.contains([',', '、']) => pick(expr, rng)?,
+        _ => roll(expr, rng)?,
+    };
+    Ok(format!("{} → {}", expr, result))
+}
+
+pub struct DiceConverter {
+    rng: RefCell<StdRng>,
+}
+
+impl DiceConverter {
+    /// A fixed seed gives the same rolls every time
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Default for DiceConverter {
+    fn default() -> Self {
+        Self {
+            rng: RefCell::new(StdRng::from_entropy()),
+        }
+    }
+}
+
+impl Converter for DiceConverter {
+    fn convert(&self, text: &str) -> Result<String> {
+        let result = evaluate(text, &mut *self.rng.borrow_mut())?;
+        debug!("Dice result: {}", result);
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "dice".to_string()
+    }
+}
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ダイス",
+        "ダイスロール (2d6+3, 4d6kh3, d100)、リストからの抽選 (a, b, c)、コイントス (coin) を行います",
+        || Box::new(DiceConverter::default()),
+    )
+    .trigger('d')
+    .alias("roll")
+    .example("2d6+3", "2d6+3 → [4,2]+3 = 9")
+    .example("4d6kh3", "4d6kh3 → [6,(1),3,5] = 14")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(text: &str, seed: u64) -> Result<String> {
+        DiceConverter::with_seed(seed).convert(text)
+    }
+
+    /// Splits `[4,(1),3]` into the kept and dropped rolls
+    fn parse_rolls(shown: &str) -> (Vec<i64>, Vec<i64>) {
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+        for roll in shown.trim_matches(['[', ']']).split(',') {
+            match roll.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+                Some(roll) => dropped.push(roll.parse().unwrap()),
+                None => kept.push(roll.parse().unwrap()),
+            }
+        }
+        (kept, dropped)
+    }
+
+    #[test]
+    fn same_seed_gives_same_rolls() {
+        for text in ["2d6+3", "4d6kh3", "d100", "a, b, c", "coin"] {
+            assert_eq!(convert(text, 7).unwrap(), convert(text, 7).unwrap());
+        }
+    }
+
+    #[test]
+    fn rolls_with_modifier() {
+        for seed in 0..20 {
+            let result = convert("2d6+3", seed).unwrap();
+            let (shown, total) = result
+                .strip_prefix("2d6+3 → ")
+                .and_then(|r| r.split_once(" = "))
+                .unwrap();
+            let (rolls, modifier) = shown.split_once('+').unwrap();
+            let (kept, dropped) = parse_rolls(rolls);
+            assert_eq!(kept.len(), 2);
+            assert!(dropped.is_empty());
+            assert!(kept.iter().all(|roll| (1..=6).contains(roll)));
+            assert_eq!(modifier, "3");
+            assert_eq!(total.parse::<i64>().unwrap(), kept.iter().sum::<i64>() + 3);
+        }
+    }
+
+    #[test]
+    fn keeps_highest() {
+        for seed in 0..20 {
+            let result = convert("4d6kh3", seed).unwrap();
+            let (shown, total) = result
+                .strip_prefix("4d6kh3 → ")
+                .and_then(|r| r.split_once(" = "))
+                .unwrap();
+            let (kept, dropped) = parse_rolls(shown);
+            assert_eq!((kept.len(), dropped.len()), (3, 1));
+            assert!(kept.iter().all(|&roll| roll >= dropped[0]));
+            assert_eq!(total.parse::<i64>().unwrap(), kept.iter().sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn rolls_d100() {
+        for seed in 0..20 {
+            let result = convert("d100", seed).unwrap();
+            let total = result.rsplit_once(" = ").unwrap().1;
+            assert!((1..=100).contains(&total.parse::<i64>().unwrap()));
+        }
+    }
+
+    #[test]
+    fn picks_and_flips() {
+        for seed in 0..20 {
+            let picked = convert("ramen、sushi、curry", seed).unwrap();
+            let picked = picked.rsplit_once(" → ").unwrap().1;
+            assert!(["ramen", "sushi", "curry"].contains(&picked));
+
+            let flipped = convert("coin", seed).unwrap();
+            assert!(COIN.contains(&flipped.rsplit_once(" → ").unwrap().1));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rolls() {
+        for text in ["0d6", "d0", "101d6", "d1000001", "4d6kh5", "2d6 3", "abc"] {
+            assert!(convert(text, 0).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_numbers() {
+        for text in [
+            "1d6+9223372036854775807",
+            "-9223372036854775807-1d6",
+            "9223372036854775807+9223372036854775807",
+            "9223372036854775808",
+            "99999999999d6",
+            "1d99999999999",
+            "4d6kh99999999999999999999",
+        ] {
+            assert!(convert(text, 0).is_err(), "{}", text);
+        }
+        assert_eq!(
+            convert("9223372036854775807-1d1", 0).unwrap(),
+            "9223372036854775807-1d1 → 9223372036854775807-[1] = 9223372036854775806"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use regex::Regex;
+use tracing::{debug, info, trace};
+
+use crate::azookey::direct::get_global_converter;
+
+use super::{
+    converter::{ConversionContext, Converter, ConverterRegistration},
+    kana,
+};
+
+const READING_OPEN: char = '(';
+const READING_CLOSE: char = ')';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FuriganaGranularity {
+    /// `漢字(かんじ)を書(か)く`
+    #[default]
+    Word,
+    /// `漢字を書く(かんじをかく)`
+    Phrase,
+}
+
+impl FuriganaGranularity {
+    fn parse(arg: &str) -> Result<Self> {
+        match arg.to_lowercase().as_str() {
+            "word" => Ok(Self::Word),
+            "phrase" => Ok(Self::Phrase),
+            _ => Err(anyhow!(
+                "Unknown furigana granularity '{}', expected 'word' or 'phrase'",
+                arg
+            )),
+        }
+    }
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '々' | '〆' | 'ヶ'
+    )
+}
+
+/// Katakana are compared as hiragana so that `コーヒー` matches `こーひー`
+fn normalize_kana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Places each kanji run's reading after it, using the kana around the run
+/// as anchors in the reading
+///
+/// Returns `None` when the kana of `text` cannot be found in `reading`.
+pub fn annotate_words(text: &str, reading: &str) -> Option<String> {
+    let runs = text
+        .chars()
+        .chunk_by(|&c| is_kanji(c))
+        .into_iter()
+        .map(|(kanji, run)| (kanji, run.collect::<String>()))
+        .collect::<Vec<_>>();
+
+    let pattern = runs
+        .iter()
+        .map(|(kanji, run)| {
+            if *kanji {
+                "(.+?)".to_string()
+            } else {
+                regex::escape(&normalize_kana(run))
+            }
+        })
+        .collect::<String>();
+    let pattern = Regex::new(&format!("^{}$", pattern)).ok()?;
+    let normalized_reading = normalize_kana(reading);
+    let caps = pattern.captures(&normalized_reading)?;
+
+    let mut readings = caps.iter().skip(1).flatten();
+    let mut result = String::new();
+    for (kanji, run) in &runs {
+        result.push_str(run);
+        if *kanji {
+            result.push(READING_OPEN);
+            result.push_str(readings.next()?.as_str());
+            result.push(READING_CLOSE);
+        }
+    }
+    Some(result)
+}
+
+/// Formats `text` with its reading; text without kanji is returned as is
+pub fn annotate(text: &str, reading: &str, granularity: FuriganaGranularity) -> String {
+    if !text.chars().any(is_kanji) {
+        return text.to_string();
+    }
+    let phrase = || format!("{}{}{}{}", text, READING_OPEN, reading, READING_CLOSE);
+    match granularity {
+        FuriganaGranularity::Word => annotate_words(text, reading).unwrap_or_else(|| {
+            debug!(
+                "Could not align '{}' with '{}', using phrase",
+                text, reading
+            );
+            phrase()
+        }),
+        FuriganaGranularity::Phrase => phrase(),
+    }
+}
+
+/// Annotates the kanji that an earlier stage produced, e.g. `;r|f`, with
+/// the kana of the block input as the reading
+///
+/// Fails when the block input itself contains kanji, as their reading is
+/// unknown.
+pub fn annotate_converted(
+    text: &str,
+    input: &str,
+    granularity: FuriganaGranularity,
+) -> Result<String> {
+    if input.chars().any(is_kanji) {
+        return Err(anyhow!(
+            "Cannot read the kanji in '{}'; type it as romaji or kana",
+            input
+        ));
+    }
+    let reading = kana::to_hiragana(input);
+    Ok(annotate(text, &reading, granularity))
+}
+
+/// Adds the reading of kanji, either converting romaji or kana with AzooKey
+/// itself (`;f`) or annotating the output of an earlier stage (`;r|f`)
+pub struct FuriganaConverter;
+
+impl FuriganaConverter {
+    /// Returns the first AzooKey candidate together with its reading
+    fn convert_with_reading(text: &str) -> Result<(String, String)> {
+        if text.chars().any(is_kanji) {
+            return Err(anyhow!(
+                "Cannot read the kanji in '{}'; type it as romaji or kana",
+                text
+            ));
+        }
+        let converter = get_global_converter();
+        converter.reset_composing_text();
+        converter.insert_at_cursor_position(text);
+        let candidates = converter.request_candidates("");
+        converter.reset_composing_text();
+        trace!("AzooKey candidates: {:?}", candidates);
+
+        candidates
+            .into_iter()
+            .next()
+            .map(|candidate| (candidate.text, candidate.hiragana))
+            .ok_or_else(|| anyhow!("No conversion candidates available for '{}'", text))
+    }
+}
+
+impl Converter for FuriganaConverter {
+    fn convert(&self, text: &str) -> Result<String> {
+        self.convert_with_args(text, &[])
+    }
+
+    /// `;furigana(word)` annotates each kanji run, `;furigana(phrase)` the whole text
+    fn convert_with_args(&self, text: &str, args: &[String]) -> Result<String> {
+        let granularity = parse_granularity(args)?;
+        let (converted, reading) = Self::convert_with_reading(text)?;
+        let result = annotate(&converted, &reading, granularity);
+        info!(
+            "Furigana conversion ({:?}): {} -> {}",
+            granularity, text, result
+        );
+        Ok(result)
+    }
+
+    /// Text that already contains kanji comes from an earlier stage and is
+    /// read from the block input instead
+    fn convert_in_context(
+        &self,
+        text: &str,
+        args: &[String],
+        context: &ConversionContext,
+    ) -> Result<String> {
+        if !text.chars().any(is_kanji) {
+            return self.convert_with_args(text, args);
+        }
+        let granularity = parse_granularity(args)?;
+        let result = annotate_converted(text, context.input, granularity)?;
+        info!(
+            "Furigana annotation ({:?}): {} ({}) -> {}",
+            granularity, text, context.input, result
+        );
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "furigana".to_string()
+    }
+}
+
+fn parse_granularity(args: &[String]) -> Result<FuriganaGranularity> {
+    match args {
+        [] => Ok(FuriganaGranularity::default()),
+        [granularity] => FuriganaGranularity::parse(granularity),
+        _ => Err(anyhow!("Expected a single granularity argument")),
+    }
+}
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ふりがな",
+        "ローマ字やかなを漢字に変換し、読みを括弧で添えます。;r|f のように変換結果に読みを付けることもできます。phrase を指定すると文全体に読みを付けます",
+        || Box::new(FuriganaConverter),
+    )
+    .trigger('f')
+    .alias("ruby")
+    .example("kannjiwokaku", "漢字(かんじ)を書(か)く")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotates_words() {
+        for (text, reading, expected) in [
+            ("漢字を書く", "かんじをかく", Some("漢字(かんじ)を書(か)く")),
+            ("東京", "とうきょう", Some("東京(とうきょう)")),
+            (
+                "コーヒーを飲む",
+                "こーひーをのむ",
+                Some("コーヒーを飲(の)む"),
+            ),
+            (
+                "日本語の勉強",
+                "にほんごのべんきょう",
+                Some("日本語(にほんご)の勉強(べんきょう)"),
+            ),
+            ("漢字を書く", "かんじでかく", None),
+        ] {
+            assert_eq!(
+                annotate_words(text, reading).as_deref(),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn annotates_phrases_and_falls_back() {
+        let phrase = FuriganaGranularity::Phrase;
+        let word = FuriganaGranularity::Word;
+        assert_eq!(
+            annotate("漢字を書く", "かんじをかく", phrase),
+            "漢字を書く(かんじをかく)"
+        );
+        assert_eq!(annotate("かな", "かな", word), "かな");
+        assert_eq!(
+            annotate("漢字を書く", "かんじでかく", word),
+            "漢字を書く(かんじでかく)"
+        );
+    }
+
+    #[test]
+    fn annotates_converted_text_from_the_input() {
+        let word = FuriganaGranularity::Word;
+        assert_eq!(
+            annotate_converted("漢字を書く", "kannjiwokaku", word).unwrap(),
+            "漢字(かんじ)を書(か)く"
+        );
+        assert_eq!(
+            annotate_converted("漢字を書く", "かんじをかく", word).unwrap(),
+            "漢字(かんじ)を書(か)く"
+        );
+        assert!(annotate_converted("漢字を書く", "漢字をかく", word).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use itertools::Itertools;
+use tracing::{debug, trace};
+
+use super::converter::{Converter, ConverterRegistration};
+
+const SYLLABLE_BASE: u32 = 0xAC00;
+const VOWEL_COUNT: u32 = 21;
+const FINAL_COUNT: u32 = 28;
+
+/// Forces a syllable boundary, e.g. `jung-ang` for `중앙` instead of `준강`
+const BOUNDARIES: [char; 2] = ['-', '\''];
+
+/// Initial consonants in Unicode order; `ㅇ` is written as an empty string
+const INITIALS: &[(&str, u32)] = &[
+    ("g", 0),
+    ("kk", 1),
+    ("n", 2),
+    ("d", 3),
+    ("tt", 4),
+    ("r", 5),
+    ("l", 5),
+    ("m", 6),
+    ("b", 7),
+    ("pp", 8),
+    ("s", 9),
+    ("ss", 10),
+    ("", 11),
+    ("j", 12),
+    ("jj", 13),
+    ("ch", 14),
+    ("k", 15),
+    ("t", 16),
+    ("p", 17),
+    ("h", 18),
+];
+
+/// Vowels longest first so that `yeo` is not read as `ye` + `o`
+const VOWELS: &[(&str, u32)] = &[
+    ("yae", 3),
+    ("yeo", 6),
+    ("wae", 10),
+    ("ae", 1),
+    ("ya", 2),
+    ("eo", 4),
+    ("ye", 7),
+    ("wa", 9),
+    ("oe", 11),
+    ("yo", 12),
+    ("wo", 14),
+    ("we", 15),
+    ("wi", 16),
+    ("yu", 17),
+    ("eu", 18),
+    ("ui", 19),
+    ("a", 0),
+    ("e", 5),
+    ("o", 8),
+    ("u", 13),
+    ("i", 20),
+];
+
+/// Final consonants; Revised Romanization writes `ㄱ` as `k`, `ㅂ` as `p` and
+/// `ㅅ` as `t` at the end of a syllable, so those spellings are accepted too
+const FINALS: &[(&str, u32)] = &[
+    ("", 0),
+    ("g", 1),
+    ("k", 1),
+    ("kk", 2),
+    ("gs", 3),
+    ("n", 4),
+    ("nj", 5),
+    ("nh", 6),
+    ("d", 7),
+    ("l", 8),
+    ("r", 8),
+    ("lg", 9),
+    ("lk", 9),
+    ("lm", 10),
+    ("lb", 11),
+    ("ls", 12),
+    ("lt", 13),
+    ("lp", 14),
+    ("lh", 15),
+    ("m", 16),
+    ("b", 17),
+    ("p", 17),
+    ("bs", 18),
+    ("ps", 18),
+    ("s", 19),
+    ("t", 19),
+    ("ss", 20),
+    ("ng", 21),
+    ("j", 22),
+    ("ch", 23),
+    ("h", 27),
+];
+
+fn lookup(table: &[(&str, u32)], key: &str) -> Option<u32> {
+    table
+        .iter()
+        .find(|(romanization, _)| *romanization == key)
+        .map(|(_, index)| *index)
+}
+
+/// `w` and `y` only appear as the start of a vowel
+fn is_vowel_letter(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'w' | 'y')
+}
+
+fn split_vowels(run: &str) -> Option<Vec<u32>> {
+    let mut vowels = Vec::new();
+    let mut rest = run;
+    while !rest.is_empty() {
+        let (romanization, index) = VOWELS
+            .iter()
+            .find(|(romanization, _)| rest.starts_with(romanization))?;
+        vowels.push(*index);
+        rest = &rest[romanization.len()..];
+    }
+    Some(vowels)
+}
+
+/// Splits the consonants between two vowels into the previous syllable's final
+/// and the next syllable's initial
+///
+/// The next syllable takes as many consonants as it can, so `hangeul` becomes
+/// `한글` rather than `항을`. `ss` is the exception: it is far more common as the
+/// past tense `ㅆ` final, as in `isseoyo` (`있어요`).
+fn split_cluster(cluster: &str) -> Option<(u32, u32)> {
+    if cluster == "ss" {
+        return Some((20, 11));
+    }
+    (0..=cluster.len()).find_map(|at| {
+        let (final_part, initial_part) = cluster.split_at(at);
+        Some((lookup(FINALS, final_part)?, lookup(INITIALS, initial_part)?))
+    })
+}
+
+fn compose(initial: u32, vowel: u32, final_consonant: u32) -> Option<char> {
+    char::from_u32(SYLLABLE_BASE + (initial * VOWEL_COUNT + vowel) * FINAL_COUNT + final_consonant)
+}
+
+/// Composes one word without boundaries; `None` if it is not romanized Korean
+fn compose_part(part: &str) -> Option<String> {
+    let lower = part.to_lowercase();
+    let runs = lower
+        .chars()
+        .chunk_by(|&c| is_vowel_letter(c))
+        .into_iter()
+        .map(|(vowel, run)| (vowel, run.collect::<String>()))
+        .collect::<Vec<_>>();
+
+    // clusters[i] precedes vowels[i]; the last cluster follows the last vowel
+    let mut clusters = vec![String::new()];
+    let mut vowels = Vec::new();
+    for (is_vowel, run) in runs {
+        if is_vowel {
+            for vowel in split_vowels(&run)? {
+                vowels.push(vowel);
+                clusters.push(String::new());
+            }
+        } else {
+            *clusters.last_mut()? = run;
+        }
+    }
+    if vowels.is_empty() {
+        return None;
+    }
+
+    let mut initials = vec![lookup(INITIALS, &clusters[0])?];
+    let mut finals = Vec::new();
+    for cluster in &clusters[1..clusters.len() - 1] {
+        let (final_consonant, initial) = split_cluster(cluster)?;
+        finals.push(final_consonant);
+        initials.push(initial);
+    }
+    finals.push(lookup(FINALS, clusters.last()?)?);
+
+    vowels
+        .iter()
+        .zip(initials)
+        .zip(finals)
+        .map(|((&vowel, initial), final_consonant)| compose(initial, vowel, final_consonant))
+        .collect()
+}
+
+/// Converts a word such as `annyeonghaseyo` or `jung-ang`, keeping it as is
+/// when it cannot be read as Revised Romanization
+fn compose_word(word: &str) -> String {
+    let parts = word.split(BOUNDARIES).collect::<Vec<_>>();
+    match parts
+        .iter()
+        .map(|part| compose_part(part))
+        .collect::<Option<String>>()
+    {
+        Some(composed) => composed,
+        None => {
+            trace!("Not romanized Korean, keeping: {}", word);
+            word.to_string()
+        }
+    }
+}
+
+/// Composes Hangul syllables from Revised Romanization, e.g. `annyeonghaseyo`
+/// -> `안녕하세요`; anything other than ASCII letters is kept
+pub fn to_hangul(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (is_word, run) in &text
+        .chars()
+        .chunk_by(|&c| c.is_ascii_alphabetic() || BOUNDARIES.contains(&c))
+    {
+        let run = run.collect::<String>();
+        if is_word {
+            result.push_str(&compose_word(&run));
+        } else {
+            result.push_str(&run);
+        }
+    }
+    result
+}
+
+pub struct HangulConverter;
+
+impl Converter for HangulConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        let result = to_hangul(text);
+        debug!("Hangul conversion: {} -> {}", text, result);
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "hangul".to_string()
+    }
+}
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ハングル変換",
+        "ローマ字 (文化観光部2000年式) をハングルに変換します。- で音節を区切れます",
+        || Box::new(HangulConverter),
+    )
+    .alias("korean")
+    .example("annyeonghaseyo", "안녕하세요")
+    .example("jung-ang", "중앙")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_hangul() {
+        let cases = [
+            ("annyeonghaseyo", "안녕하세요"),
+            ("Hangeul", "한글"),
+            ("ilgop", "일곱"),
+            ("seoul", "서울"),
+            ("saranghae", "사랑해"),
+            ("isseoyo", "있어요"),
+            ("jungang", "준강"),
+            ("jung-ang", "중앙"),
+            ("han'guk", "한국"),
+            ("annyeong, chingu!", "안녕, 친구!"),
+            ("a-", "a-"),
+            ("-", "-"),
+            ("strike", "strike"),
+            ("xyz 123", "xyz 123"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(to_hangul(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn splits_clusters() {
+        let cases = [
+            ("", Some((0, 11))),
+            ("n", Some((0, 2))),
+            ("kk", Some((0, 1))),
+            ("ng", Some((4, 0))),
+            ("lg", Some((8, 0))),
+            ("ngg", Some((21, 0))),
+            ("mn", Some((16, 2))),
+            ("ss", Some((20, 11))),
+            ("x", None),
+        ];
+        for (cluster, expected) in cases {
+            assert_eq!(split_cluster(cluster), expected, "{}", cluster);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(target_os = "windows")]
+use tracing::warn;
 use tracing::{debug, info, trace};
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::Input::Ime::{
@@ -6,7 +8,10 @@ use windows::Win32::UI::Input::Ime::{
 
 use crate::felanguage::FElanguage;
 
-use super::converter::Converter;
+use super::{
+    converter::{Converter, ConverterRegistration},
+    kana,
+};
 
 #[derive(Clone)]
 pub struct HiraganaConverter;
@@ -15,7 +20,16 @@ impl Converter for HiraganaConverter {
     #[cfg(target_os = "windows")]
     fn convert(&self, text: &str) -> anyhow::Result<String> {
         debug!("Converting to hiragana: {}", text);
-        let felanguage = FElanguage::new()?;
+        let felanguage = match FElanguage::new() {
+            Ok(felanguage) => felanguage,
+            Err(e) => {
+                warn!(
+                    "MS-IME is not available, using portable hiragana conversion: {}",
+                    e
+                );
+                return Ok(kana::to_hiragana(text));
+            }
+        };
         trace!("FElanguage instance created");
 
         let result = felanguage.j_morph_result(
@@ -34,8 +48,8 @@ impl Converter for HiraganaConverter {
 
     #[cfg(not(target_os = "windows"))]
     fn convert(&self, text: &str) -> anyhow::Result<String> {
-        debug!("Converting to hiragana (no-op on non-Windows): {}", text);
-        Ok(text.to_string())
+        debug!("Converting to hiragana (portable): {}", text);
+        Ok(kana::to_hiragana(text))
     }
 
     fn name(&self) -> String {
@@ -43,3 +57,14 @@ impl Converter for HiraganaConverter {
         "hiragana".to_string()
     }
 }
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ひらがな変換",
+        "入力をひらがなに変換します",
+        || Box::new(HiraganaConverter),
+    )
+    .trigger('h')
+    .alias("hira")
+    .example("漢字", "かんじ")
+}
@@ -0,0 +1,179 @@
+use itertools::Itertools;
+use tracing::{debug, trace};
+use wana_kana::ConvertJapanese;
+
+use crate::azookey::processing::replace_signs;
+
+use super::converter::{Converter, ConverterRegistration};
+
+#[derive(Clone, Copy, Debug)]
+enum KanaKind {
+    Hiragana,
+    Katakana,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Romaji,
+    Kana,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    match c {
+        _ if c.is_ascii_alphabetic() || c == '\'' => CharClass::Romaji,
+        // `ー` is left out, as `wana_kana` turns it into a vowel in hiragana
+        '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}' => CharClass::Kana,
+        _ => CharClass::Other,
+    }
+}
+
+/// IMEs read `nn` as a single `ん`, while `wana_kana` reads it as `んん`, so
+/// `kannji` and `konnnichiha` become `kan'ji` and `kon'nichiha`; an `n`
+/// before a vowel or `y` still starts a syllable, as in `konnichiha`
+fn separate_double_n(romaji: &str) -> String {
+    let chars = romaji.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(romaji.len());
+    let mut index = 0;
+    while index < chars.len() {
+        if !chars[index].eq_ignore_ascii_case(&'n') {
+            result.push(chars[index]);
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while chars
+            .get(index)
+            .is_some_and(|c| c.eq_ignore_ascii_case(&'n'))
+        {
+            index += 1;
+        }
+        let starts_syllable = chars
+            .get(index)
+            .is_some_and(|c| "aiueoy".contains(c.to_ascii_lowercase()));
+        let end = if starts_syllable { index - 1 } else { index };
+        for pair in chars[start..end].chunks(2) {
+            result.push(pair[0]);
+            result.push('\'');
+        }
+        if starts_syllable {
+            result.push(chars[end]);
+        } else if chars.get(index) == Some(&'\'') {
+            // `hon'ya` already has its separator
+            index += 1;
+        }
+    }
+    result
+}
+
+/// Converts romaji and mixed kana without MS-IME
+///
+/// Signs are replaced like `azookey::processing::pre_process_text`; unlike AzooKey,
+/// `wana_kana` already turns a trailing `n` into `ん`. `wana_kana` leaves the whole
+/// text untouched once it contains kanji or full-width punctuation, so romaji and
+/// kana runs are converted separately from everything else, e.g. the kana of
+/// `漢字とかな` still change.
+fn to_kana(text: &str, kind: KanaKind) -> String {
+    let prepared = replace_signs(text);
+    trace!("Prepared text for {:?}: {}", kind, prepared);
+
+    let mut result = String::with_capacity(prepared.len());
+    for (class, run) in &prepared.chars().chunk_by(|&c| char_class(c)) {
+        let run = run.collect::<String>();
+        let run = match class {
+            CharClass::Romaji => separate_double_n(&run),
+            _ => run,
+        };
+        let converted = match (class, kind) {
+            (CharClass::Other, _) => run,
+            (_, KanaKind::Hiragana) => run.to_hiragana(),
+            (_, KanaKind::Katakana) => run.to_katakana(),
+        };
+        result.push_str(&converted);
+    }
+    result
+}
+
+pub fn to_hiragana(text: &str) -> String {
+    let result = to_kana(text, KanaKind::Hiragana);
+    debug!("Portable hiragana conversion: {} -> {}", text, result);
+    result
+}
+
+pub fn to_katakana(text: &str) -> String {
+    let result = to_kana(text, KanaKind::Katakana);
+    debug!("Portable katakana conversion: {} -> {}", text, result);
+    result
+}
+
+#[derive(Clone)]
+pub struct PortableHiraganaConverter;
+
+impl Converter for PortableHiraganaConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        Ok(to_hiragana(text))
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "portable_hiragana".to_string()
+    }
+}
+
+#[derive(Clone)]
+pub struct PortableKatakanaConverter;
+
+impl Converter for PortableKatakanaConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        Ok(to_katakana(text))
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "portable_katakana".to_string()
+    }
+}
+
+pub fn hiragana_registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ひらがな変換 (内蔵)",
+        "MS-IMEを使わずにローマ字やカタカナをひらがなに変換します",
+        || Box::new(PortableHiraganaConverter),
+    )
+    .trigger('H')
+    .example("konnichiha", "こんにちは")
+}
+
+pub fn katakana_registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "カタカナ変換 (内蔵)",
+        "MS-IMEを使わずにローマ字やひらがなをカタカナに変換します",
+        || Box::new(PortableKatakanaConverter),
+    )
+    .trigger('K')
+    .example("ra-men", "ラーメン")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_like_an_ime() {
+        for (text, hiragana) in [
+            ("konnichiha", "こんにちは"),
+            ("konnnichiha", "こんにちは"),
+            ("kannji", "かんじ"),
+            ("minnna", "みんな"),
+            ("hon'ya", "ほんや"),
+            ("sannpo", "さんぽ"),
+            ("hon", "ほん"),
+            ("honn", "ほん"),
+            ("ra-men", "らーめん"),
+            ("漢字toかな", "漢字とかな"),
+        ] {
+            assert_eq!(to_hiragana(text), hiragana, "{}", text);
+        }
+        assert_eq!(to_katakana("ra-mennnonna"), "ラーメンノンナ");
+    }
+}
@@ -0,0 +1,396 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::{debug, trace};
+
+use super::converter::{Converter, ConverterRegistration};
+
+const DIGITS: [char; 10] = ['〇', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+const DAIJI_DIGITS: [char; 10] = ['零', '壱', '弐', '参', '肆', '伍', '陸', '漆', '捌', '玖'];
+/// 十, 百, 千 within a group of four digits
+const SMALL_UNITS: [char; 3] = ['十', '百', '千'];
+const DAIJI_SMALL_UNITS: [char; 3] = ['拾', '百', '阡'];
+/// 万, 億, 兆, 京 for each group of four digits
+const LARGE_UNITS: [char; 4] = ['万', '億', '兆', '京'];
+const DAIJI_LARGE_UNITS: [char; 4] = ['萬', '億', '兆', '京'];
+/// 1京 × 10000; numbers from here on have no unit
+const LIMIT: u128 = 10u128.pow(20);
+
+/// Arabic digits, optionally grouped with commas, in half or full width; the
+/// fraction is matched so that decimals can be left as they are
+static ARABIC_NUMBER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:[0-9０-９]{1,3}(?:,[0-9０-９]{3})+|[0-9０-９]+)(?:\.[0-9０-９]+)?").unwrap()
+});
+static KANJI_NUMBER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        "[〇零一二三四五六七八九十百千万億兆京壱壹弐貳参參肆伍陸漆捌玖拾佰阡仟萬]\
+         [0-9〇零一二三四五六七八九十百千万億兆京壱壹弐貳参參肆伍陸漆捌玖拾佰阡仟萬]*|\
+         [0-9]+[万億兆京萬][0-9〇零一二三四五六七八九十百千万億兆京萬]*",
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KansujiStyle {
+    /// `百二十三`
+    #[default]
+    Kanji,
+    /// `壱百弐拾参`
+    Daiji,
+    /// `1万2000`
+    Mixed,
+    /// `百二十三` -> `123`
+    Arabic,
+}
+
+impl KansujiStyle {
+    fn parse(arg: &str) -> Result<Self> {
+        match arg.to_lowercase().as_str() {
+            "kanji" => Ok(Self::Kanji),
+            "daiji" | "大字" => Ok(Self::Daiji),
+            "mixed" => Ok(Self::Mixed),
+            "arabic" | "digits" | "number" => Ok(Self::Arabic),
+            _ => Err(anyhow!(
+                "Unknown kansuji style '{}', expected kanji, daiji, mixed or arabic",
+                arg
+            )),
+        }
+    }
+}
+
+/// Splits a number into groups of four digits, lowest first
+fn groups(number: u128) -> Vec<u32> {
+    let mut groups = Vec::new();
+    let mut rest = number;
+    while rest > 0 {
+        groups.push((rest % 10000) as u32);
+        rest /= 10000;
+    }
+    groups
+}
+
+fn group_to_kanji(group: u32, daiji: bool) -> String {
+    let (digits, units) = if daiji {
+        (DAIJI_DIGITS, DAIJI_SMALL_UNITS)
+    } else {
+        (DIGITS, SMALL_UNITS)
+    };
+    let mut result = String::new();
+    for (position, unit) in [
+        (1000, Some(units[2])),
+        (100, Some(units[1])),
+        (10, Some(units[0])),
+        (1, None),
+    ] {
+        let digit = (group / position % 10) as usize;
+        if digit == 0 {
+            continue;
+        }
+        // 大字 always writes the leading 壱 so that it cannot be altered
+        if digit != 1 || unit.is_none() || daiji {
+            result.push(digits[digit]);
+        }
+        result.extend(unit);
+    }
+    result
+}
+
+/// `123` -> `百二十三`, `壱百弐拾参` or `123`; `12000` -> `一万二千` or `1万2000`
+pub fn to_kansuji(number: u128, style: KansujiStyle) -> Result<String> {
+    if number >= LIMIT {
+        return Err(anyhow!("{} is too large for kansuji", number));
+    }
+    if number == 0 {
+        return Ok(match style {
+            KansujiStyle::Kanji => DIGITS[0].to_string(),
+            KansujiStyle::Daiji => DAIJI_DIGITS[0].to_string(),
+            KansujiStyle::Mixed | KansujiStyle::Arabic => "0".to_string(),
+        });
+    }
+    if style == KansujiStyle::Arabic {
+        return Ok(number.to_string());
+    }
+
+    let mut result = String::new();
+    for (index, group) in groups(number).into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        match style {
+            KansujiStyle::Mixed => result.push_str(&group.to_string()),
+            _ => result.push_str(&group_to_kanji(group, style == KansujiStyle::Daiji)),
+        }
+        if index > 0 {
+            let units = if style == KansujiStyle::Daiji {
+                DAIJI_LARGE_UNITS
+            } else {
+                LARGE_UNITS
+            };
+            result.push(units[index - 1]);
+        }
+    }
+    Ok(result)
+}
+
+fn digit_value(c: char) -> Option<u128> {
+    match c {
+        '0'..='9' => c.to_digit(10).map(u128::from),
+        _ => DIGITS
+            .iter()
+            .position(|&d| d == c)
+            .or_else(|| DAIJI_DIGITS.iter().position(|&d| d == c))
+            .or(match c {
+                '壹' => Some(1),
+                '貳' => Some(2),
+                '參' => Some(3),
+                _ => None,
+            })
+            .map(|value| value as u128),
+    }
+}
+
+fn unit_value(c: char) -> Option<u128> {
+    match c {
+        '十' | '拾' => Some(10),
+        '百' | '佰' => Some(100),
+        '千' | '阡' | '仟' => Some(1000),
+        _ => None,
+    }
+}
+
+fn large_unit_value(c: char) -> Option<u128> {
+    match c {
+        '万' | '萬' => Some(10u128.pow(4)),
+        '億' => Some(10u128.pow(8)),
+        '兆' => Some(10u128.pow(12)),
+        '京' => Some(10u128.pow(16)),
+        _ => None,
+    }
+}
+
+/// Parses `百二十三`, `壱百弐拾参`, `1万2000` or digit by digit `二〇二五`
+pub fn parse_kansuji(text: &str) -> Result<u128> {
+    let overflow = || anyhow!("'{}' is too large", text);
+    let mut total: u128 = 0;
+    let mut section: u128 = 0;
+    let mut current: u128 = 0;
+    for c in text.chars() {
+        if let Some(digit) = digit_value(c) {
+            current = current
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit))
+                .ok_or_else(overflow)?;
+        } else if let Some(unit) = unit_value(c) {
+            section = current
+                .max(1)
+                .checked_mul(unit)
+                .and_then(|value| value.checked_add(section))
+                .ok_or_else(overflow)?;
+            current = 0;
+        } else if let Some(unit) = large_unit_value(c) {
+            let value = section.checked_add(current).ok_or_else(overflow)?.max(1);
+            total = value
+                .checked_mul(unit)
+                .and_then(|value| value.checked_add(total))
+                .ok_or_else(overflow)?;
+            section = 0;
+            current = 0;
+        } else {
+            return Err(anyhow!("Unexpected '{}' in '{}'", c, text));
+        }
+    }
+    total
+        .checked_add(section)
+        .and_then(|value| value.checked_add(current))
+        .ok_or_else(overflow)
+}
+
+fn parse_arabic(text: &str) -> Result<u128> {
+    let digits = text
+        .chars()
+        .filter(|&c| c != ',')
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap_or(c),
+            _ => c,
+        })
+        .collect::<String>();
+    digits
+        .parse::<u128>()
+        .map_err(|e| anyhow!("Invalid number '{}': {}", text, e))
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々')
+}
+
+/// Whether a match is a number on its own rather than part of a word or a
+/// decimal: kanji runs next to other kanji such as `十分` and `千葉` are
+/// words, and `3.14` or `1.2.3` keep their digits
+fn is_complete_number(text: &str, start: usize, end: usize, style: KansujiStyle) -> bool {
+    let number = &text[start..end];
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    match style {
+        KansujiStyle::Arabic => {
+            number.contains(|c: char| c.is_ascii_digit())
+                || !(before.is_some_and(is_kanji) || after.is_some_and(is_kanji))
+        }
+        _ => !number.contains('.') && before != Some('.'),
+    }
+}
+
+/// Converts every number in `text` to `style`
+pub fn convert_numbers(text: &str, style: KansujiStyle) -> Result<String> {
+    let pattern = match style {
+        KansujiStyle::Arabic => &KANJI_NUMBER,
+        _ => &ARABIC_NUMBER,
+    };
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for found in pattern.find_iter(text) {
+        if !is_complete_number(text, found.start(), found.end(), style) {
+            trace!("Keeping number: {}", found.as_str());
+            continue;
+        }
+        result.push_str(&text[last..found.start()]);
+        let number = match style {
+            KansujiStyle::Arabic => parse_kansuji(found.as_str())?,
+            _ => parse_arabic(found.as_str())?,
+        };
+        let converted = to_kansuji(number, style)?;
+        trace!("Number converted: {} -> {}", found.as_str(), converted);
+        result.push_str(&converted);
+        last = found.end();
+    }
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+pub struct KansujiConverter;
+
+impl Converter for KansujiConverter {
+    fn convert(&self, text: &str) -> Result<String> {
+        self.convert_with_args(text, &[])
+    }
+
+    /// `;kansuji(daiji)`, `;kansuji(mixed)` or `;kansuji(arabic)` to parse back
+    fn convert_with_args(&self, text: &str, args: &[String]) -> Result<String> {
+        let style = match args {
+            [] => KansujiStyle::default(),
+            [style] => KansujiStyle::parse(style)?,
+            _ => return Err(anyhow!("Expected a single style argument")),
+        };
+        let result = convert_numbers(text, style)?;
+        debug!("Kansuji conversion ({:?}): {} -> {}", style, text, result);
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "kansuji".to_string()
+    }
+}
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "漢数字変換",
+        "数字を漢数字に変換します。引数で大字 (daiji)、万進 (mixed)、算用数字への逆変換 (arabic) を選べます",
+        || Box::new(KansujiConverter),
+    )
+    .alias("kanji_number")
+    .example("123", "百二十三")
+    .example("12000", "一万二千")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_kansuji() {
+        let cases = [
+            (0, KansujiStyle::Kanji, "〇"),
+            (10, KansujiStyle::Kanji, "十"),
+            (123, KansujiStyle::Kanji, "百二十三"),
+            (1010, KansujiStyle::Kanji, "千十"),
+            (12000, KansujiStyle::Kanji, "一万二千"),
+            (100_000_000, KansujiStyle::Kanji, "一億"),
+            (1_0000_0000_0000, KansujiStyle::Kanji, "一兆"),
+            (1_0000_0000_0000_0000, KansujiStyle::Kanji, "一京"),
+            (
+                9999_9999_9999_9999_9999,
+                KansujiStyle::Kanji,
+                "九千九百九十九京九千九百九十九兆九千九百九十九億九千九百九十九万九千九百九十九",
+            ),
+            (0, KansujiStyle::Daiji, "零"),
+            (10, KansujiStyle::Daiji, "壱拾"),
+            (123, KansujiStyle::Daiji, "壱百弐拾参"),
+            (1000, KansujiStyle::Daiji, "壱阡"),
+            (20000, KansujiStyle::Daiji, "弐萬"),
+            (0, KansujiStyle::Mixed, "0"),
+            (32000, KansujiStyle::Mixed, "3万2000"),
+            (100_000_001, KansujiStyle::Mixed, "1億1"),
+            (123, KansujiStyle::Arabic, "123"),
+        ];
+        for (number, style, expected) in cases {
+            assert_eq!(
+                to_kansuji(number, style).unwrap(),
+                expected,
+                "{} {:?}",
+                number,
+                style
+            );
+        }
+        assert!(to_kansuji(LIMIT, KansujiStyle::Kanji).is_err());
+    }
+
+    #[test]
+    fn parses_kansuji() {
+        let cases = [
+            ("十", 10),
+            ("百二十三", 123),
+            ("千十", 1010),
+            ("二〇二五", 2025),
+            ("壱百弐拾参", 123),
+            ("壹萬", 10000),
+            ("参阡", 3000),
+            ("3万2千", 32000),
+            ("1万2000", 12000),
+            ("十京", 10u128.pow(17)),
+            (
+                "九千九百九十九京九千九百九十九兆九千九百九十九億九千九百九十九万九千九百九十九",
+                9999_9999_9999_9999_9999,
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_kansuji(text).unwrap(), expected, "{}", text);
+        }
+        assert!(parse_kansuji("百x").is_err());
+    }
+
+    #[test]
+    fn converts_numbers_in_text() {
+        let cases = [
+            ("1,000円", KansujiStyle::Kanji, "千円"),
+            ("１２３個", KansujiStyle::Kanji, "百二十三個"),
+            ("3.14と2", KansujiStyle::Kanji, "3.14と二"),
+            ("v1.2.3", KansujiStyle::Kanji, "v1.2.3"),
+            ("金10000円", KansujiStyle::Daiji, "金壱萬円"),
+            ("123456789人", KansujiStyle::Mixed, "1億2345万6789人"),
+            ("3万2千人", KansujiStyle::Arabic, "32000人"),
+            ("百二十三です", KansujiStyle::Arabic, "123です"),
+            ("百二十三円", KansujiStyle::Arabic, "百二十三円"),
+            ("十分に千葉で", KansujiStyle::Arabic, "十分に千葉で"),
+            ("千葉で三つ", KansujiStyle::Arabic, "千葉で3つ"),
+        ];
+        for (text, style, expected) in cases {
+            assert_eq!(
+                convert_numbers(text, style).unwrap(),
+                expected,
+                "{} {:?}",
+                text,
+                style
+            );
+        }
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(target_os = "windows")]
+use tracing::warn;
 use tracing::{debug, info, trace};
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::Input::Ime::{
@@ -6,7 +8,10 @@ use windows::Win32::UI::Input::Ime::{
 
 use crate::felanguage::FElanguage;
 
-use super::converter::Converter;
+use super::{
+    converter::{Converter, ConverterRegistration},
+    kana,
+};
 
 #[derive(Clone)]
 pub struct KatakanaConverter;
@@ -15,7 +20,16 @@ impl Converter for KatakanaConverter {
     #[cfg(target_os = "windows")]
     fn convert(&self, text: &str) -> anyhow::Result<String> {
         debug!("Converting to katakana: {}", text);
-        let felanguage = FElanguage::new()?;
+        let felanguage = match FElanguage::new() {
+            Ok(felanguage) => felanguage,
+            Err(e) => {
+                warn!(
+                    "MS-IME is not available, using portable katakana conversion: {}",
+                    e
+                );
+                return Ok(kana::to_katakana(text));
+            }
+        };
         trace!("FElanguage instance created");
 
         let result = felanguage.j_morph_result(
@@ -34,8 +48,8 @@ impl Converter for KatakanaConverter {
 
     #[cfg(not(target_os = "windows"))]
     fn convert(&self, text: &str) -> anyhow::Result<String> {
-        debug!("Converting to katakana (no-op on non-Windows): {}", text);
-        Ok(text.to_string())
+        debug!("Converting to katakana (portable): {}", text);
+        Ok(kana::to_katakana(text))
     }
 
     fn name(&self) -> String {
@@ -43,3 +57,14 @@ impl Converter for KatakanaConverter {
         "katakana".to_string()
     }
 }
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "カタカナ変換",
+        "入力をカタカナに変換します",
+        || Box::new(KatakanaConverter),
+    )
+    .trigger('k')
+    .alias("kata")
+    .example("漢字", "カンジ")
+}
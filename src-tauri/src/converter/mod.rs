@@ -1,6 +1,16 @@
 pub mod calculator;
 pub mod converter;
+pub mod dice;
+pub mod furigana;
+pub mod hangul;
 pub mod hiragana;
+pub mod kana;
+pub mod kansuji;
 pub mod katakana;
-mod none_converter;
+pub mod none_converter;
 pub mod roman_to_kanji;
+pub mod text_style;
+pub mod timezone;
+pub mod units;
+pub mod unknown;
+pub mod width;
@@ -1,4 +1,4 @@
-use super::converter::Converter;
+use super::converter::{Converter, ConverterRegistration};
 use tracing::{debug, trace};
 
 pub struct NoneConverter;
@@ -14,3 +14,11 @@ impl Converter for NoneConverter {
         "none".to_string()
     }
 }
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new("無変換", "入力をそのまま出力します", || {
+        Box::new(NoneConverter)
+    })
+    .trigger('n')
+    .example("hello", "hello")
+}
@@ -1,46 +1,115 @@
-use tracing::{debug, info, trace};
+#[cfg(not(target_os = "windows"))]
+use std::convert::Infallible;
+
+use anyhow::anyhow;
+use tracing::{debug, info, trace, warn};
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::Input::Ime::{
     FELANG_CMODE_HIRAGANAOUT, FELANG_CMODE_NOINVISIBLECHAR, FELANG_CMODE_PRECONV,
     FELANG_CMODE_ROMAN, FELANG_REQ_CONV,
 };
 
+#[cfg(target_os = "windows")]
 use crate::felanguage::FElanguage;
+use crate::{azookey::direct::get_global_converter, COMPILED_DICTIONARY};
 
-use super::converter::Converter;
+use super::converter::{Converter, ConverterRegistration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RomanToKanjiBackend {
+    FElanguage,
+    Azookey,
+}
+
+/// Converts with MS-IME when `ms_ime` could be opened, otherwise with AzooKey
+fn convert_with_backend<T>(
+    ms_ime: anyhow::Result<T>,
+    with_ms_ime: impl FnOnce(T) -> anyhow::Result<String>,
+    with_azookey: impl FnOnce() -> anyhow::Result<String>,
+) -> (RomanToKanjiBackend, anyhow::Result<String>) {
+    match ms_ime {
+        Ok(ms_ime) => {
+            trace!("MS-IME is available");
+            (RomanToKanjiBackend::FElanguage, with_ms_ime(ms_ime))
+        }
+        Err(e) => {
+            warn!("MS-IME is not available, falling back to AzooKey: {}", e);
+            (RomanToKanjiBackend::Azookey, with_azookey())
+        }
+    }
+}
 
 pub struct RomanToKanjiConverter;
 
-impl Converter for RomanToKanjiConverter {
+impl RomanToKanjiConverter {
     #[cfg(target_os = "windows")]
-    fn convert(&self, text: &str) -> anyhow::Result<String> {
-        debug!("Converting roman to kanji: {}", text);
-        let felanguage = FElanguage::new()?;
-        trace!("FElanguage instance created");
+    fn open_ms_ime() -> anyhow::Result<FElanguage> {
+        FElanguage::new()
+    }
 
-        let result = felanguage.j_morph_result(
+    #[cfg(not(target_os = "windows"))]
+    fn open_ms_ime() -> anyhow::Result<Infallible> {
+        Err(anyhow!("MS-IME is only available on Windows"))
+    }
+
+    #[cfg(target_os = "windows")]
+    fn convert_with_felanguage(felanguage: FElanguage, text: &str) -> anyhow::Result<String> {
+        felanguage.j_morph_result(
             text,
             FELANG_REQ_CONV,
             FELANG_CMODE_HIRAGANAOUT
                 | FELANG_CMODE_ROMAN
                 | FELANG_CMODE_NOINVISIBLECHAR
                 | FELANG_CMODE_PRECONV,
-        );
+        )
+    }
 
-        match &result {
-            Ok(converted) => info!("Conversion successful: {} -> {}", text, converted),
-            Err(e) => debug!("Conversion failed: {}", e),
-        }
+    #[cfg(not(target_os = "windows"))]
+    fn convert_with_felanguage(felanguage: Infallible, _text: &str) -> anyhow::Result<String> {
+        match felanguage {}
+    }
 
-        result
+    fn convert_with_azookey(text: &str) -> anyhow::Result<String> {
+        let converter = get_global_converter();
+        let user_dictionary = COMPILED_DICTIONARY.read().unwrap().user_dictionary.clone();
+        converter.set_user_dictionary(user_dictionary);
+
+        converter.reset_composing_text();
+        converter.insert_at_cursor_position(text);
+        let candidates = converter.request_candidate_texts("");
+        converter.reset_composing_text();
+        trace!("AzooKey candidates: {:?}", candidates);
+
+        candidates
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No conversion candidates available for '{}'", text))
     }
 
-    #[cfg(not(target_os = "windows"))]
+    /// Uses FElanguage when MS-IME is available, otherwise the AzooKey engine
+    fn convert_with_available_backend(text: &str) -> (RomanToKanjiBackend, anyhow::Result<String>) {
+        convert_with_backend(
+            Self::open_ms_ime(),
+            |felanguage| Self::convert_with_felanguage(felanguage, text),
+            || Self::convert_with_azookey(text),
+        )
+    }
+}
+
+impl Converter for RomanToKanjiConverter {
     fn convert(&self, text: &str) -> anyhow::Result<String> {
-        debug!("Convert method called on non-Windows platform");
-        Err(anyhow::anyhow!(
-            "Roman to Kanji conversion is only supported on Windows."
-        ))
+        debug!("Converting roman to kanji: {}", text);
+        let (backend, result) = Self::convert_with_available_backend(text);
+
+        match &result {
+            Ok(converted) => info!(
+                "Conversion successful ({:?}): {} -> {}",
+                backend, text, converted
+            ),
+            Err(e) => debug!("Conversion failed ({:?}): {}", backend, e),
+        }
+
+        result
     }
 
     fn name(&self) -> String {
@@ -48,3 +117,51 @@ impl Converter for RomanToKanjiConverter {
         "roman_to_kanji".to_string()
     }
 }
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "ローマ字→漢字",
+        "ローマ字を漢字に変換します",
+        || Box::new(RomanToKanjiConverter),
+    )
+    .trigger('r')
+    .alias("kanji")
+    .example("kannji", "漢字")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(ms_ime: anyhow::Result<&str>) -> (RomanToKanjiBackend, String) {
+        let (backend, result) = convert_with_backend(
+            ms_ime,
+            |ms_ime| Ok(format!("{}: 漢字", ms_ime)),
+            || Ok("AzooKey: 漢字".to_string()),
+        );
+        (backend, result.unwrap())
+    }
+
+    #[test]
+    fn selects_the_available_backend() {
+        assert_eq!(
+            convert(Ok("MS-IME")),
+            (RomanToKanjiBackend::FElanguage, "MS-IME: 漢字".to_string())
+        );
+        assert_eq!(
+            convert(Err(anyhow!("MSIME.Japan is not registered"))),
+            (RomanToKanjiBackend::Azookey, "AzooKey: 漢字".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_the_error_of_the_selected_backend() {
+        let (backend, result) = convert_with_backend(
+            Ok(()),
+            |_| Err(anyhow!("conversion failed")),
+            || Ok("AzooKey: 漢字".to_string()),
+        );
+        assert_eq!(backend, RomanToKanjiBackend::FElanguage);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use tracing::{debug, trace};
+
+use super::{
+    converter::{Converter, ConverterRegistration},
+    width::to_full_width,
+};
+
+const SMALL_CAPS: &str = "ᴀʙᴄᴅᴇꜰɢʜɪᴊᴋʟᴍɴᴏᴘǫʀꜱᴛᴜᴠᴡxʏᴢ";
+
+const UPSIDE_DOWN_FROM: &str =
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,'\"!?()[]{}<>_&;";
+const UPSIDE_DOWN_TO: &str =
+    "ɐqɔpǝɟƃɥᴉɾʞlɯuodbɹsʇnʌʍxʎz∀ꓭƆᗡƎℲ⅁HIſꓘ˥WNOԀΌᴚS⊥∩ΛMX⅄Z0ƖᄅƐㄣϛ9ㄥ86˙'‚„¡¿)(][}{><‾⅋؛";
+
+/// Combining long stroke overlay, drawn through the preceding character
+const STRIKE: char = '\u{0336}';
+
+static UPSIDE_DOWN: Lazy<HashMap<char, char>> = Lazy::new(|| {
+    UPSIDE_DOWN_FROM
+        .chars()
+        .zip(UPSIDE_DOWN_TO.chars())
+        .collect()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    UpsideDown,
+    Bold,
+    Italic,
+    Monospace,
+    SmallCaps,
+    Fullwidth,
+    Strikethrough,
+}
+
+/// Code points left reserved in the Mathematical Alphanumeric Symbols blocks
+/// because the letters were already encoded as Letterlike Symbols
+const LETTERLIKE_EXCEPTIONS: &[(u32, char)] = &[
+    // Italic
+    (0x1D455, 'ℎ'),
+    // Script
+    (0x1D49D, 'ℬ'),
+    (0x1D4A0, 'ℰ'),
+    (0x1D4A1, 'ℱ'),
+    (0x1D4A3, 'ℋ'),
+    (0x1D4A4, 'ℐ'),
+    (0x1D4A7, 'ℒ'),
+    (0x1D4A8, 'ℳ'),
+    (0x1D4AD, 'ℛ'),
+    (0x1D4BA, 'ℯ'),
+    (0x1D4BC, 'ℊ'),
+    (0x1D4C4, 'ℴ'),
+    // Fraktur
+    (0x1D506, 'ℭ'),
+    (0x1D50B, 'ℌ'),
+    (0x1D50C, 'ℑ'),
+    (0x1D515, 'ℜ'),
+    (0x1D51D, 'ℨ'),
+    // Double-struck
+    (0x1D53A, 'ℂ'),
+    (0x1D53F, 'ℍ'),
+    (0x1D545, 'ℕ'),
+    (0x1D547, 'ℙ'),
+    (0x1D548, 'ℚ'),
+    (0x1D549, 'ℝ'),
+    (0x1D551, 'ℤ'),
+];
+
+/// Maps ASCII letters and digits into a Mathematical Alphanumeric Symbols block
+fn math_alphanumeric(c: char, upper: u32, lower: u32, digit: Option<u32>) -> char {
+    let code = match c {
+        'A'..='Z' => upper + (c as u32 - 'A' as u32),
+        'a'..='z' => lower + (c as u32 - 'a' as u32),
+        '0'..='9' => match digit {
+            Some(digit) => digit + (c as u32 - '0' as u32),
+            None => return c,
+        },
+        _ => return c,
+    };
+    match LETTERLIKE_EXCEPTIONS
+        .iter()
+        .find(|(reserved, _)| *reserved == code)
+    {
+        Some((_, letterlike)) => *letterlike,
+        None => char::from_u32(code).unwrap_or(c),
+    }
+}
+
+/// Applies `style` to the ASCII letters, digits and punctuation in `text`
+pub fn apply(text: &str, style: TextStyle) -> String {
+    match style {
+        TextStyle::UpsideDown => text
+            .chars()
+            .rev()
+            .map(|c| UPSIDE_DOWN.get(&c).copied().unwrap_or(c))
+            .collect(),
+        TextStyle::Bold => text
+            .chars()
+            .map(|c| math_alphanumeric(c, 0x1D400, 0x1D41A, Some(0x1D7CE)))
+            .collect(),
+        TextStyle::Italic => text
+            .chars()
+            .map(|c| math_alphanumeric(c, 0x1D434, 0x1D44E, None))
+            .collect(),
+        TextStyle::Monospace => text
+            .chars()
+            .map(|c| math_alphanumeric(c, 0x1D670, 0x1D68A, Some(0x1D7F6)))
+            .collect(),
+        TextStyle::SmallCaps => text
+            .chars()
+            .map(|c| match c {
+                'a'..='z' => SMALL_CAPS
+                    .chars()
+                    .nth(c as usize - 'a' as usize)
+                    .unwrap_or(c),
+                _ => c,
+            })
+            .collect(),
+        TextStyle::Fullwidth => to_full_width(text),
+        TextStyle::Strikethrough => text
+            .chars()
+            .flat_map(|c| {
+                if c.is_whitespace() {
+                    vec![c]
+                } else {
+                    vec![c, STRIKE]
+                }
+            })
+            .collect(),
+    }
+}
+
+pub struct TextStyleConverter {
+    style: TextStyle,
+}
+
+impl Converter for TextStyleConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        let result = apply(text, self.style);
+        debug!(
+            "Text style conversion ({:?}): {} -> {}",
+            self.style, text, result
+        );
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        match self.style {
+            TextStyle::UpsideDown => "upside_down",
+            TextStyle::Bold => "bold",
+            TextStyle::Italic => "italic",
+            TextStyle::Monospace => "monospace",
+            TextStyle::SmallCaps => "small_caps",
+            TextStyle::Fullwidth => "aesthetic",
+            TextStyle::Strikethrough => "strike",
+        }
+        .to_string()
+    }
+}
+
+pub fn registrations() -> Vec<ConverterRegistration> {
+    vec![
+        ConverterRegistration::new(
+            "上下反転",
+            "英数字を上下逆さまにして、文字の並びも反転します",
+            || {
+                Box::new(TextStyleConverter {
+                    style: TextStyle::UpsideDown,
+                })
+            },
+        )
+        .alias("flip")
+        .example("Hello!", "¡ollǝH"),
+        ConverterRegistration::new("太字", "英数字を数学用太字にします", || {
+            Box::new(TextStyleConverter {
+                style: TextStyle::Bold,
+            })
+        })
+        .example("Bold 1", "𝐁𝐨𝐥𝐝 𝟏"),
+        ConverterRegistration::new("斜体", "英字を数学用斜体にします", || {
+            Box::new(TextStyleConverter {
+                style: TextStyle::Italic,
+            })
+        })
+        .example("Italic", "𝐼𝑡𝑎𝑙𝑖𝑐"),
+        ConverterRegistration::new(
+            "等幅",
+            "英数字を数学用等幅文字にします",
+            || {
+                Box::new(TextStyleConverter {
+                    style: TextStyle::Monospace,
+                })
+            },
+        )
+        .alias("mono")
+        .example("code 42", "𝚌𝚘𝚍𝚎 𝟺𝟸"),
+        ConverterRegistration::new(
+            "スモールキャップ",
+            "小文字をスモールキャップにします",
+            || {
+                Box::new(TextStyleConverter {
+                    style: TextStyle::SmallCaps,
+                })
+            },
+        )
+        .alias("smallcaps")
+        .example("small caps", "ꜱᴍᴀʟʟ ᴄᴀᴘꜱ"),
+        ConverterRegistration::new(
+            "全角エステティック",
+            "英数字と空白を全角にして間隔の広い文字にします",
+            || {
+                Box::new(TextStyleConverter {
+                    style: TextStyle::Fullwidth,
+                })
+            },
+        )
+        .alias("vapor")
+        .example("aesthetic", "ａｅｓｔｈｅｔｉｃ"),
+        ConverterRegistration::new(
+            "取り消し線",
+            "結合文字で各文字に取り消し線を付けます",
+            || {
+                Box::new(TextStyleConverter {
+                    style: TextStyle::Strikethrough,
+                })
+            },
+        )
+        .alias("strikethrough")
+        .example("no", "n̶o̶"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_styles() {
+        let cases = [
+            ("Hello!", TextStyle::UpsideDown, "¡ollǝH"),
+            ("ab 12?", TextStyle::UpsideDown, "¿ᄅƖ qɐ"),
+            ("Bold 1", TextStyle::Bold, "𝐁𝐨𝐥𝐝 𝟏"),
+            ("Az09", TextStyle::Bold, "𝐀𝐳𝟎𝟗"),
+            ("Italic", TextStyle::Italic, "𝐼𝑡𝑎𝑙𝑖𝑐"),
+            ("high 5", TextStyle::Italic, "ℎ𝑖𝑔ℎ 5"),
+            ("code 42", TextStyle::Monospace, "𝚌𝚘𝚍𝚎 𝟺𝟸"),
+            ("Small caps", TextStyle::SmallCaps, "Sᴍᴀʟʟ ᴄᴀᴘꜱ"),
+            ("abc!", TextStyle::Fullwidth, "ａｂｃ！"),
+            ("a b", TextStyle::Strikethrough, "a\u{0336} b\u{0336}"),
+            ("あ漢字", TextStyle::Bold, "あ漢字"),
+            ("あ漢字", TextStyle::UpsideDown, "字漢あ"),
+        ];
+        for (input, style, expected) in cases {
+            assert_eq!(apply(input, style), expected, "{:?} {}", style, input);
+        }
+    }
+
+    #[test]
+    fn uses_letterlike_symbols_for_reserved_code_points() {
+        let cases = [
+            ('h', 0x1D434, 0x1D44E, 'ℎ'),
+            ('B', 0x1D49C, 0x1D4B6, 'ℬ'),
+            ('e', 0x1D49C, 0x1D4B6, 'ℯ'),
+            ('A', 0x1D49C, 0x1D4B6, '𝒜'),
+            ('C', 0x1D504, 0x1D51E, 'ℭ'),
+            ('D', 0x1D504, 0x1D51E, '𝔇'),
+            ('R', 0x1D538, 0x1D552, 'ℝ'),
+        ];
+        for (c, upper, lower, expected) in cases {
+            assert_eq!(math_alphanumeric(c, upper, lower, None), expected, "{}", c);
+        }
+
+        let alphanumerics = ('A'..='Z').chain('a'..='z').chain('0'..='9');
+        for style in [TextStyle::Bold, TextStyle::Italic, TextStyle::Monospace] {
+            let styled = apply(&alphanumerics.clone().collect::<String>(), style);
+            assert!(
+                styled.chars().all(|c| !LETTERLIKE_EXCEPTIONS
+                    .iter()
+                    .any(|(reserved, _)| c as u32 == *reserved)),
+                "{:?}",
+                style
+            );
+        }
+    }
+}
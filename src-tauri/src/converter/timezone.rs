@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use tracing::{debug, trace};
+
+use super::converter::{Converter, ConverterRegistration};
+
+const TIME_FORMAT: &str = "%H:%M %Z";
+const SEPARATORS: [&str; 5] = ["to", "in", "->", "→", "=>"];
+
+/// Abbreviations people actually type in event announcements; DST variants map to
+/// the same zone so that `PST` in summer still means Los Angeles time
+const ABBREVIATIONS: &[(&str, Tz)] = &[
+    ("JST", Tz::Asia__Tokyo),
+    ("KST", Tz::Asia__Seoul),
+    ("HKT", Tz::Asia__Hong_Kong),
+    ("SGT", Tz::Asia__Singapore),
+    ("IST", Tz::Asia__Kolkata),
+    ("UTC", Tz::UTC),
+    ("GMT", Tz::UTC),
+    ("Z", Tz::UTC),
+    ("WET", Tz::Europe__Lisbon),
+    ("BST", Tz::Europe__London),
+    ("CET", Tz::Europe__Berlin),
+    ("CEST", Tz::Europe__Berlin),
+    ("EET", Tz::Europe__Helsinki),
+    ("EEST", Tz::Europe__Helsinki),
+    ("MSK", Tz::Europe__Moscow),
+    ("ET", Tz::America__New_York),
+    ("EST", Tz::America__New_York),
+    ("EDT", Tz::America__New_York),
+    ("CT", Tz::America__Chicago),
+    ("CST", Tz::America__Chicago),
+    ("CDT", Tz::America__Chicago),
+    ("MT", Tz::America__Denver),
+    ("MST", Tz::America__Denver),
+    ("MDT", Tz::America__Denver),
+    ("PT", Tz::America__Los_Angeles),
+    ("PST", Tz::America__Los_Angeles),
+    ("PDT", Tz::America__Los_Angeles),
+    ("AKST", Tz::America__Anchorage),
+    ("AKDT", Tz::America__Anchorage),
+    ("HST", Tz::Pacific__Honolulu),
+    ("BRT", Tz::America__Sao_Paulo),
+    ("AWST", Tz::Australia__Perth),
+    ("ACST", Tz::Australia__Adelaide),
+    ("ACDT", Tz::Australia__Adelaide),
+    ("AEST", Tz::Australia__Sydney),
+    ("AEDT", Tz::Australia__Sydney),
+    ("NZST", Tz::Pacific__Auckland),
+    ("NZDT", Tz::Pacific__Auckland),
+];
+
+/// Accepts an abbreviation such as `JST` or an IANA name such as `europe/berlin`
+pub fn parse_zone(name: &str) -> Option<Tz> {
+    let upper = name.to_uppercase();
+    ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == upper)
+        .map(|(_, tz)| *tz)
+        .or_else(|| name.parse::<Tz>().ok())
+        .or_else(|| {
+            TZ_VARIANTS
+                .iter()
+                .find(|tz| tz.name().eq_ignore_ascii_case(name))
+                .copied()
+        })
+}
+
+/// The system time zone, or UTC if it cannot be determined
+pub fn local_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+fn parse_time(token: &str) -> Option<NaiveTime> {
+    let lower = token.to_lowercase();
+    let (clock, meridiem) = if let Some(clock) = lower.strip_suffix("am") {
+        (clock, Some(false))
+    } else if let Some(clock) = lower.strip_suffix("pm") {
+        (clock, Some(true))
+    } else {
+        (lower.as_str(), None)
+    };
+
+    let (hour, minute) = if let Some((hour, rest)) = clock.split_once('時') {
+        (hour, rest.strip_suffix('分').unwrap_or(rest))
+    } else if let Some((hour, minute)) = clock.split_once(':') {
+        (hour, minute)
+    } else if meridiem.is_some() {
+        (clock, "")
+    } else {
+        return None;
+    };
+    let mut hour = hour.parse::<u32>().ok()?;
+    let minute = if minute.is_empty() {
+        0
+    } else {
+        minute.parse::<u32>().ok()?
+    };
+
+    if let Some(pm) = meridiem {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// `2025-03-01`, `2025/03/01` or `3/1` (in the current year)
+fn parse_date(token: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let parts = token
+        .split(['-', '/'])
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match parts[..] {
+        [year, month, day] => NaiveDate::from_ymd_opt(year as i32, month, day),
+        [month, day] => NaiveDate::from_ymd_opt(today.year(), month, day),
+        _ => None,
+    }
+}
+
+fn parse_day_offset(token: &str) -> Option<i64> {
+    match token.to_lowercase().as_str() {
+        "yesterday" | "昨日" => Some(-1),
+        "today" | "今日" => Some(0),
+        "tomorrow" | "明日" => Some(1),
+        _ => None,
+    }
+}
+
+/// Splits whitespace-separated tokens, joining `9 pm` into `9pm`
+fn split_tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        let is_meridiem = matches!(token.to_lowercase().as_str(), "am" | "pm");
+        match tokens.last_mut() {
+            Some(last) if is_meridiem && last.chars().all(|c| c.is_ascii_digit() || c == ':') => {
+                last.push_str(token)
+            }
+            _ => tokens.push(token.to_string()),
+        }
+    }
+    tokens
+}
+
+/// Resolves the source side of an expression, e.g. `tomorrow 9pm PST` or `now`
+fn parse_source(tokens: &[String], now: DateTime<Utc>, local: Tz) -> Result<(DateTime<Tz>, Tz)> {
+    let mut zone = None;
+    let mut time = None;
+    let mut is_now = false;
+    let mut day_offset = 0;
+    let mut date = None;
+
+    for token in tokens {
+        if matches!(token.to_lowercase().as_str(), "now" | "今") {
+            is_now = true;
+        } else if let Some(offset) = parse_day_offset(token) {
+            day_offset = offset;
+        } else if let Some(parsed) = parse_time(token) {
+            time = Some(parsed);
+        } else if let Some(tz) = parse_zone(token) {
+            zone = Some(tz);
+        } else if let Some(parsed) = parse_date(token, now.with_timezone(&local).date_naive()) {
+            date = Some(parsed);
+        } else {
+            return Err(anyhow!("Unrecognized '{}'", token));
+        }
+    }
+
+    let zone = zone.unwrap_or(local);
+    if is_now {
+        return Ok((now.with_timezone(&zone), zone));
+    }
+    let time = time.ok_or_else(|| anyhow!("Expected a time such as 21:00 or 9pm"))?;
+    let date =
+        date.unwrap_or_else(|| now.with_timezone(&zone).date_naive()) + TimeDelta::days(day_offset);
+    let source = zone
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(|| anyhow!("{} {} does not exist in {}", date, time, zone.name()))?;
+    Ok((source, zone))
+}
+
+fn format_in(source: &DateTime<Tz>, tz: Tz) -> String {
+    let converted = source.with_timezone(&tz);
+    let mut formatted = converted.format(TIME_FORMAT).to_string();
+    match (converted.date_naive() - source.date_naive()).num_days() {
+        0 => {}
+        1 => formatted.push_str("(翌日)"),
+        -1 => formatted.push_str("(前日)"),
+        days => formatted.push_str(&format!("({:+}日)", days)),
+    }
+    formatted
+}
+
+/// Converts `21:00 JST`, `tomorrow 9pm PST to JST` or `now in Europe/Berlin`
+///
+/// Target zones come from the expression, then from `default_targets`, and
+/// finally fall back to `local`. When the only target is the source zone, the
+/// source time is returned as is. `now` and `local` are passed in so the
+/// result only depends on the arguments.
+pub fn convert_expression(
+    text: &str,
+    default_targets: &[Tz],
+    now: DateTime<Utc>,
+    local: Tz,
+) -> Result<String> {
+    let tokens = split_tokens(text);
+    let separator = tokens
+        .iter()
+        .position(|token| SEPARATORS.contains(&token.to_lowercase().as_str()));
+    let (source_tokens, target_tokens) = match separator {
+        Some(index) => (&tokens[..index], &tokens[index + 1..]),
+        None => (&tokens[..], &[][..]),
+    };
+    trace!("Source: {:?}, targets: {:?}", source_tokens, target_tokens);
+
+    let mut targets = target_tokens
+        .iter()
+        .flat_map(|token| token.split(','))
+        .filter(|name| !name.is_empty())
+        .map(|name| parse_zone(name).ok_or_else(|| anyhow!("Unknown time zone '{}'", name)))
+        .collect::<Result<Vec<_>>>()?;
+    if let (Some(index), true) = (separator, targets.is_empty()) {
+        return Err(anyhow!("Expected a time zone after '{}'", tokens[index]));
+    }
+
+    let (source, zone) = parse_source(source_tokens, now, local)?;
+    if targets.is_empty() {
+        targets = default_targets.to_vec();
+    }
+    if targets.is_empty() {
+        targets.push(local);
+    }
+    targets.retain(|&tz| tz != zone);
+    if targets.is_empty() {
+        return Ok(source.format(TIME_FORMAT).to_string());
+    }
+
+    let converted = targets
+        .iter()
+        .map(|&tz| format_in(&source, tz))
+        .collect::<Vec<_>>()
+        .join(" / ");
+    Ok(format!("{} = {}", source.format(TIME_FORMAT), converted))
+}
+
+pub struct TimeZoneConverter {
+    clock: fn() -> DateTime<Utc>,
+}
+
+impl TimeZoneConverter {
+    pub fn with_clock(clock: fn() -> DateTime<Utc>) -> Self {
+        Self { clock }
+    }
+}
+
+impl Default for TimeZoneConverter {
+    fn default() -> Self {
+        Self::with_clock(Utc::now)
+    }
+}
+
+impl Converter for TimeZoneConverter {
+    fn convert(&self, text: &str) -> Result<String> {
+        self.convert_with_args(text, &[])
+    }
+
+    /// `;tz(Asia/Tokyo, PST)` sets the target zones used when the text has no `to ...`
+    fn convert_with_args(&self, text: &str, args: &[String]) -> Result<String> {
+        let default_targets = args
+            .iter()
+            .map(|name| parse_zone(name).ok_or_else(|| anyhow!("Unknown time zone '{}'", name)))
+            .collect::<Result<Vec<_>>>()?;
+        let result = convert_expression(text, &default_targets, (self.clock)(), local_zone())?;
+        debug!("Time zone conversion: {} -> {}", text, result);
+        Ok(result)
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "timezone".to_string()
+    }
+}
+
+pub fn registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "タイムゾーン変換",
+        "時刻を別のタイムゾーンに変換します",
+        || Box::new(TimeZoneConverter::default()),
+    )
+    .trigger('t')
+    .alias("tz")
+    .example("21:00 JST to UTC", "21:00 JST = 12:00 UTC")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-01-15 12:00 JST, 2025-01-14 19:00 PST
+    fn fixed_clock() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 15, 3, 0, 0).unwrap()
+    }
+
+    fn convert(text: &str, args: &[&str]) -> String {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        TimeZoneConverter::with_clock(fixed_clock)
+            .convert_with_args(text, &args)
+            .unwrap()
+    }
+
+    #[test]
+    fn converts_time_to_default_target() {
+        assert_eq!(convert("21:00 JST", &["UTC"]), "21:00 JST = 12:00 UTC");
+    }
+
+    #[test]
+    fn converts_tomorrow_across_date_line() {
+        assert_eq!(
+            convert("tomorrow 9pm PST to JST", &[]),
+            "21:00 PST = 14:00 JST(翌日)"
+        );
+    }
+
+    #[test]
+    fn converts_now() {
+        assert!(convert("now in Europe/Berlin", &[]).ends_with("04:00 CET"));
+        assert_eq!(
+            convert_expression("now in Europe/Berlin", &[], fixed_clock(), Tz::Asia__Tokyo)
+                .unwrap(),
+            "12:00 JST = 04:00 CET"
+        );
+    }
+
+    #[test]
+    fn source_in_local_zone_is_kept() {
+        assert_eq!(
+            convert_expression("21:00 JST", &[], fixed_clock(), Tz::Asia__Tokyo).unwrap(),
+            "21:00 JST"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_zone() {
+        assert!(convert_expression("21:00 JST to Mars", &[], fixed_clock(), Tz::UTC).is_err());
+    }
+}
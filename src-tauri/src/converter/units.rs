@@ -0,0 +1,439 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, trace};
+
+use crate::config::Config;
+
+/// `5 km in mi`, `30C to F` or `180cm -> ft`; the last separator wins
+static SEPARATOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s+(?:in|to)\s+|\s*(?:->|→)\s*").unwrap());
+
+const DEFAULT_RATES: &str = r#"# Currency rates used by unit conversion such as `;c 1000 JPY in USD`
+# Each rate is the value of one unit of the currency in `base`.
+# Rates are never fetched automatically. Add the currencies you need with
+# current values and set `updated` to the date you looked them up; the date is
+# shown next to every currency conversion.
+#
+# base: JPY
+# updated: 2024-01-31
+# rates:
+#   USD: 150.0
+#   EUR: 160.0
+base: JPY
+updated: null
+rates: {}
+"#;
+
+/// The parsed rates file, re-read only when its modification time changes
+static RATES_FILE: Lazy<Mutex<Option<(SystemTime, CurrencyRates)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Speed,
+    Temperature,
+    Data,
+    Time,
+}
+
+/// `base = (value + offset) * factor`; the first name is used for display
+struct Unit {
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit(
+        &["mm", "millimeter", "millimeters"],
+        Dimension::Length,
+        0.001,
+    ),
+    unit(
+        &["cm", "centimeter", "centimeters"],
+        Dimension::Length,
+        0.01,
+    ),
+    unit(&["m", "meter", "meters"], Dimension::Length, 1.0),
+    unit(
+        &["km", "kilometer", "kilometers"],
+        Dimension::Length,
+        1000.0,
+    ),
+    unit(&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    unit(&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    unit(&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    unit(&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    unit(&["mg"], Dimension::Mass, 0.000001),
+    unit(&["g", "gram", "grams"], Dimension::Mass, 0.001),
+    unit(&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.0),
+    unit(&["t", "ton", "tons"], Dimension::Mass, 1000.0),
+    unit(&["oz", "ounce", "ounces"], Dimension::Mass, 0.028349523125),
+    unit(
+        &["lb", "lbs", "pound", "pounds"],
+        Dimension::Mass,
+        0.45359237,
+    ),
+    unit(&["mL", "ml"], Dimension::Volume, 0.001),
+    unit(&["L", "l", "liter", "liters"], Dimension::Volume, 1.0),
+    unit(&["fl oz", "floz"], Dimension::Volume, 0.0295735295625),
+    unit(
+        &["gal", "gallon", "gallons"],
+        Dimension::Volume,
+        3.785411784,
+    ),
+    unit(&["m/s"], Dimension::Speed, 1.0),
+    unit(&["km/h", "kmh", "kph"], Dimension::Speed, 1.0 / 3.6),
+    unit(&["mph"], Dimension::Speed, 0.44704),
+    unit(&["kn", "knot", "knots"], Dimension::Speed, 0.514444),
+    Unit {
+        names: &["°C", "℃", "C", "degC"],
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        names: &["°F", "℉", "F", "degF"],
+        dimension: Dimension::Temperature,
+        factor: 5.0 / 9.0,
+        offset: 459.67,
+    },
+    unit(&["K", "kelvin"], Dimension::Temperature, 1.0),
+    unit(&["B", "byte", "bytes"], Dimension::Data, 1.0),
+    unit(&["KB"], Dimension::Data, 1e3),
+    unit(&["MB"], Dimension::Data, 1e6),
+    unit(&["GB"], Dimension::Data, 1e9),
+    unit(&["TB"], Dimension::Data, 1e12),
+    unit(&["KiB"], Dimension::Data, 1024.0),
+    unit(&["MiB"], Dimension::Data, 1048576.0),
+    unit(&["GiB"], Dimension::Data, 1073741824.0),
+    unit(&["TiB"], Dimension::Data, 1099511627776.0),
+    unit(&["ms"], Dimension::Time, 0.001),
+    unit(&["s", "sec", "second", "seconds"], Dimension::Time, 1.0),
+    unit(&["min", "minute", "minutes"], Dimension::Time, 60.0),
+    unit(&["h", "hr", "hour", "hours"], Dimension::Time, 3600.0),
+    unit(&["d", "day", "days"], Dimension::Time, 86400.0),
+];
+
+/// Finds a unit by name, preferring an exact match over a case-insensitive one
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name))
+        .or_else(|| {
+            UNITS
+                .iter()
+                .find(|unit| unit.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+        })
+}
+
+/// User-editable currency rates stored in `rates.yaml` next to `config.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyRates {
+    pub base: String,
+    #[serde(default)]
+    pub updated: Option<String>,
+    pub rates: HashMap<String, f64>,
+}
+
+impl CurrencyRates {
+    /// Loads `rates.yaml`, reusing the parsed file while it is unchanged
+    pub fn load() -> Result<Self> {
+        debug!("Loading currency rates");
+        let path = Self::get_path();
+        if !Path::new(&path).exists() {
+            info!("Currency rates file not found, generating default");
+            Self::generate_default_rates()?;
+        }
+        let modified = std::fs::metadata(&path)?.modified()?;
+
+        let mut rates_file = RATES_FILE.lock().unwrap();
+        if let Some((cached_modified, rates)) = rates_file.as_ref() {
+            if *cached_modified == modified {
+                trace!("Using cached currency rates");
+                return Ok(rates.clone());
+            }
+        }
+
+        let mut file = File::open(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let rates: CurrencyRates = serde_yaml::from_str(&contents)?;
+        trace!("Currency rates loaded: {:?}", rates);
+        *rates_file = Some((modified, rates.clone()));
+        Ok(rates)
+    }
+
+    pub fn generate_default_rates() -> Result<()> {
+        debug!("Generating default currency rates");
+        std::fs::create_dir_all(Config::get_path())?;
+        let mut file = File::create(Self::get_path())?;
+        file.write_all(DEFAULT_RATES.as_bytes())?;
+        file.flush()?;
+        info!("Default currency rates generated successfully");
+        Ok(())
+    }
+
+    pub fn get_path() -> PathBuf {
+        let path = Config::get_path().join("rates.yaml");
+        trace!("Currency rates path: {:?}", path);
+        path
+    }
+
+    /// Returns the normalized code and its value in `base`
+    fn rate(&self, code: &str) -> Result<(String, f64)> {
+        let code = code.to_uppercase();
+        if code == self.base.to_uppercase() {
+            return Ok((code, 1.0));
+        }
+        self.rates
+            .iter()
+            .find(|(c, _)| c.to_uppercase() == code)
+            .map(|(_, rate)| (code.clone(), *rate))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown unit or no rate for '{}'; add it to {}",
+                    code,
+                    Self::get_path().display()
+                )
+            })
+    }
+
+    /// Converts between two currencies, noting the date of the rates
+    fn convert(&self, amount: f64, from: &str, to: &str) -> Result<UnitConversion> {
+        let (from_code, from_rate) = self.rate(from)?;
+        let (to_code, to_rate) = self.rate(to)?;
+        debug!(
+            "Currency rates: 1 {} = {} {}, 1 {} = {} {}",
+            from_code, from_rate, self.base, to_code, to_rate, self.base
+        );
+        let note = match &self.updated {
+            Some(updated) => format!("{}時点のレート", updated),
+            None => "レートの日付不明".to_string(),
+        };
+        Ok(UnitConversion {
+            from: from_code,
+            to: to_code,
+            value: amount * from_rate / to_rate,
+            note: Some(note),
+        })
+    }
+}
+
+/// A conversion such as `5 km in mi` split into its parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitExpression {
+    /// Calculator expression for the amount, e.g. `5` or `2*3`
+    pub amount: String,
+    pub from: String,
+    pub to: String,
+}
+
+fn is_boundary(rest: &str) -> bool {
+    !rest.chars().last().is_some_and(|c| c.is_alphabetic())
+}
+
+/// Splits the trailing unit or currency code off `left`
+fn split_unit(left: &str) -> Option<(&str, &str)> {
+    let unit_name = UNITS
+        .iter()
+        .flat_map(|unit| unit.names.iter())
+        .filter(|name| {
+            left.len() >= name.len()
+                && left.is_char_boundary(left.len() - name.len())
+                && left[left.len() - name.len()..].eq_ignore_ascii_case(name)
+                && is_boundary(&left[..left.len() - name.len()])
+        })
+        .max_by_key(|name| name.len());
+    let split_at = match unit_name {
+        Some(name) => left.len() - name.len(),
+        None => left
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .len(),
+    };
+    let (amount, unit) = left.split_at(split_at);
+    (!unit.is_empty() && !amount.trim().is_empty()).then(|| (amount.trim(), unit))
+}
+
+/// Returns `None` when the text is not a unit conversion, so it can be evaluated
+/// as a plain calculator expression
+pub fn parse(text: &str) -> Option<UnitExpression> {
+    let separator = SEPARATOR.find_iter(text).last()?;
+    let left = text[..separator.start()].trim();
+    let to = text[separator.end()..].trim();
+    if to.is_empty() {
+        return None;
+    }
+    let (amount, from) = split_unit(left)?;
+    let expression = UnitExpression {
+        amount: amount.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    trace!("Unit expression: {:?}", expression);
+    Some(expression)
+}
+
+#[derive(Debug, Clone)]
+pub struct UnitConversion {
+    pub from: String,
+    pub to: String,
+    pub value: f64,
+    /// Shown after the result, e.g. the date of the currency rates
+    pub note: Option<String>,
+}
+
+/// Converts `amount` between two units of the same dimension or two currencies
+pub fn convert(amount: f64, from: &str, to: &str) -> Result<UnitConversion> {
+    match (find_unit(from), find_unit(to)) {
+        (Some(from_unit), Some(to_unit)) => {
+            if from_unit.dimension != to_unit.dimension {
+                return Err(anyhow!(
+                    "Cannot convert {} to {}",
+                    from_unit.names[0],
+                    to_unit.names[0]
+                ));
+            }
+            let base = (amount + from_unit.offset) * from_unit.factor;
+            let value = base / to_unit.factor - to_unit.offset;
+            Ok(UnitConversion {
+                from: from_unit.names[0].to_string(),
+                to: to_unit.names[0].to_string(),
+                value,
+                note: None,
+            })
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            let unknown = if find_unit(from).is_none() { from } else { to };
+            Err(anyhow!("Unknown unit '{}'", unknown))
+        }
+        (None, None) => CurrencyRates::load()?.convert(amount, from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(amount: &str, from: &str, to: &str) -> Option<UnitExpression> {
+        Some(UnitExpression {
+            amount: amount.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+
+    fn rates(yaml: &str) -> CurrencyRates {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn parses_unit_expressions() {
+        let cases = [
+            ("5 km in mi", expression("5", "km", "mi")),
+            ("30C to F", expression("30", "C", "F")),
+            ("180cm -> ft", expression("180", "cm", "ft")),
+            ("2*3 fl oz → mL", expression("2*3", "fl oz", "mL")),
+            ("1000 JPY in USD", expression("1000", "JPY", "USD")),
+            ("x m/s in km/h", expression("x", "m/s", "km/h")),
+            ("1 inch in cm", expression("1", "inch", "cm")),
+            ("1 + 2", None),
+            ("km in mi", None),
+            ("5 km in ", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn converts_units() {
+        let cases = [
+            (5.0, "km", "mi", "km", "mi", 3.106856),
+            (30.0, "C", "F", "°C", "°F", 86.0),
+            (0.0, "K", "°C", "K", "°C", -273.15),
+            (1.0, "GiB", "MB", "GiB", "MB", 1073.741824),
+            (90.0, "MIN", "h", "min", "h", 1.5),
+            (1.0, "m", "M", "m", "m", 1.0),
+        ];
+        for (amount, from, to, from_name, to_name, expected) in cases {
+            let conversion = convert(amount, from, to).unwrap();
+            assert_eq!(
+                (conversion.from.as_str(), conversion.to.as_str()),
+                (from_name, to_name)
+            );
+            assert!(
+                (conversion.value - expected).abs() < 1e-6,
+                "{} {} in {} = {}",
+                amount,
+                from,
+                to,
+                conversion.value
+            );
+            assert!(conversion.note.is_none());
+        }
+        assert!(convert(1.0, "km", "kg").is_err());
+        assert!(convert(1.0, "km", "parsec").is_err());
+    }
+
+    #[test]
+    fn converts_currencies_with_the_rate_date() {
+        let rates = rates("base: JPY\nupdated: 2024-01-31\nrates:\n  USD: 150.0\n  eur: 160.0\n");
+        let cases = [
+            ("JPY", "USD", 1500.0, "JPY", "USD", 10.0),
+            ("usd", "jpy", 2.0, "USD", "JPY", 300.0),
+            ("EUR", "USD", 15.0, "EUR", "USD", 16.0),
+        ];
+        for (from, to, amount, from_code, to_code, expected) in cases {
+            let conversion = rates.convert(amount, from, to).unwrap();
+            assert_eq!(
+                (conversion.from.as_str(), conversion.to.as_str()),
+                (from_code, to_code)
+            );
+            assert!((conversion.value - expected).abs() < 1e-9);
+            assert_eq!(conversion.note.as_deref(), Some("2024-01-31時点のレート"));
+        }
+        assert!(rates.convert(1.0, "JPY", "GBP").is_err());
+    }
+
+    #[test]
+    fn ships_no_rates() {
+        let rates = rates(DEFAULT_RATES);
+        assert_eq!(rates.base, "JPY");
+        assert!(rates.updated.is_none());
+        assert!(rates.rates.is_empty());
+        assert!(rates.convert(1.0, "JPY", "USD").is_err());
+
+        let undated = CurrencyRates {
+            updated: None,
+            rates: HashMap::from([("USD".to_string(), 150.0)]),
+            ..rates
+        };
+        let conversion = undated.convert(1.0, "USD", "JPY").unwrap();
+        assert_eq!(conversion.note.as_deref(), Some("レートの日付不明"));
+    }
+}
@@ -0,0 +1,37 @@
+use tracing::{trace, warn};
+
+use super::converter::Converter;
+
+/// Used for blocks whose trigger is not registered, so the mistake is visible
+/// in the output instead of the text being sent unconverted
+pub struct UnknownConverter {
+    pub name: String,
+}
+
+impl Converter for UnknownConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        warn!("Unknown converter '{}' for: {}", self.name, text);
+        Ok(format!("[unknown converter '{}'] {}", self.name, text))
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        "unknown".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_the_text() {
+        let converter = UnknownConverter {
+            name: "x".to_string(),
+        };
+        assert_eq!(
+            converter.convert("abc").unwrap(),
+            "[unknown converter 'x'] abc"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use tracing::{debug, trace};
+
+use super::converter::{Converter, ConverterRegistration};
+
+/// Converted to full width only; `to_half_width` keeps Japanese punctuation
+const HALF_PUNCTUATION: &str = "｡｢｣､･";
+const FULL_PUNCTUATION: &str = "。「」、・";
+const HALF_KATAKANA: &str = "ｦｧｨｩｪｫｬｭｮｯｰｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜﾝﾞﾟ";
+const FULL_KATAKANA: &str = "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+const VOICED_BASE: &str = "ウカキクケコサシスセソタチツテトハヒフヘホ";
+const VOICED: &str = "ヴガギグゲゴザジズゼゾダヂヅデドバビブベボ";
+const SEMI_VOICED_BASE: &str = "ハヒフヘホ";
+const SEMI_VOICED: &str = "パピプペポ";
+
+const HALF_DAKUTEN: char = 'ﾞ';
+const HALF_HANDAKUTEN: char = 'ﾟ';
+const IDEOGRAPHIC_SPACE: char = '\u{3000}';
+/// Offset between `!`..=`~` and `！`..=`～`
+const FULL_ASCII_OFFSET: u32 = 0xFEE0;
+
+fn zip_map(from: &str, to: &str) -> HashMap<char, char> {
+    from.chars().zip(to.chars()).collect()
+}
+
+static HALF_TO_FULL_KATAKANA: Lazy<HashMap<char, char>> = Lazy::new(|| {
+    let mut map = zip_map(HALF_KATAKANA, FULL_KATAKANA);
+    map.extend(zip_map(HALF_PUNCTUATION, FULL_PUNCTUATION));
+    map
+});
+static FULL_TO_HALF_KATAKANA: Lazy<HashMap<char, char>> =
+    Lazy::new(|| zip_map(FULL_KATAKANA, HALF_KATAKANA));
+static VOICE: Lazy<HashMap<char, char>> = Lazy::new(|| zip_map(VOICED_BASE, VOICED));
+static UNVOICE: Lazy<HashMap<char, char>> = Lazy::new(|| zip_map(VOICED, VOICED_BASE));
+static SEMI_VOICE: Lazy<HashMap<char, char>> = Lazy::new(|| zip_map(SEMI_VOICED_BASE, SEMI_VOICED));
+static SEMI_UNVOICE: Lazy<HashMap<char, char>> =
+    Lazy::new(|| zip_map(SEMI_VOICED, SEMI_VOICED_BASE));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidthDirection {
+    ToFull,
+    ToHalf,
+}
+
+impl WidthDirection {
+    fn parse(arg: &str) -> anyhow::Result<Self> {
+        match arg.to_lowercase().as_str() {
+            "full" | "zen" | "zenkaku" | "全角" => Ok(Self::ToFull),
+            "half" | "han" | "hankaku" | "半角" => Ok(Self::ToHalf),
+            _ => Err(anyhow!(
+                "Unknown width direction '{}', expected 'full' or 'half'",
+                arg
+            )),
+        }
+    }
+}
+
+/// Converts ASCII, digits and half-width katakana to full width, composing
+/// `ｶﾞ` into `ガ`
+pub fn to_full_width(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let converted = match c {
+            ' ' => IDEOGRAPHIC_SPACE,
+            '!'..='~' => char::from_u32(c as u32 + FULL_ASCII_OFFSET).unwrap_or(c),
+            _ => HALF_TO_FULL_KATAKANA.get(&c).copied().unwrap_or(c),
+        };
+        let composed = match chars.peek() {
+            Some(&HALF_DAKUTEN) => VOICE.get(&converted),
+            Some(&HALF_HANDAKUTEN) => SEMI_VOICE.get(&converted),
+            _ => None,
+        };
+        match composed {
+            Some(&composed) => {
+                chars.next();
+                result.push(composed);
+            }
+            None => result.push(converted),
+        }
+    }
+    result
+}
+
+/// Converts full-width ASCII, digits and katakana to half width, splitting
+/// `ガ` into `ｶﾞ`; katakana without a half-width form and Japanese punctuation
+/// such as `。` and `「」` are kept
+pub fn to_half_width(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == IDEOGRAPHIC_SPACE {
+            result.push(' ');
+        } else if ('！'..='～').contains(&c) {
+            result.push(char::from_u32(c as u32 - FULL_ASCII_OFFSET).unwrap_or(c));
+        } else if let Some(base) = UNVOICE.get(&c) {
+            result.push(FULL_TO_HALF_KATAKANA[base]);
+            result.push(HALF_DAKUTEN);
+        } else if let Some(base) = SEMI_UNVOICE.get(&c) {
+            result.push(FULL_TO_HALF_KATAKANA[base]);
+            result.push(HALF_HANDAKUTEN);
+        } else {
+            result.push(FULL_TO_HALF_KATAKANA.get(&c).copied().unwrap_or(c));
+        }
+    }
+    result
+}
+
+pub struct WidthConverter {
+    direction: WidthDirection,
+}
+
+impl WidthConverter {
+    fn convert_to(&self, text: &str, direction: WidthDirection) -> String {
+        let result = match direction {
+            WidthDirection::ToFull => to_full_width(text),
+            WidthDirection::ToHalf => to_half_width(text),
+        };
+        debug!("Width conversion ({:?}): {} -> {}", direction, text, result);
+        result
+    }
+}
+
+impl Converter for WidthConverter {
+    fn convert(&self, text: &str) -> anyhow::Result<String> {
+        Ok(self.convert_to(text, self.direction))
+    }
+
+    /// `;zenkaku(half)` and `;hankaku(full)` override the default direction
+    fn convert_with_args(&self, text: &str, args: &[String]) -> anyhow::Result<String> {
+        let direction = match args {
+            [] => self.direction,
+            [direction] => WidthDirection::parse(direction)?,
+            _ => return Err(anyhow!("Expected a single direction argument")),
+        };
+        Ok(self.convert_to(text, direction))
+    }
+
+    fn name(&self) -> String {
+        trace!("Getting converter name");
+        match self.direction {
+            WidthDirection::ToFull => "zenkaku".to_string(),
+            WidthDirection::ToHalf => "hankaku".to_string(),
+        }
+    }
+}
+
+pub fn zenkaku_registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "全角変換",
+        "英数字と半角カタカナを全角に変換します",
+        || {
+            Box::new(WidthConverter {
+                direction: WidthDirection::ToFull,
+            })
+        },
+    )
+    .trigger('z')
+    .alias("zen")
+    .example("ｶﾞｯｺｳ ABC123", "ガッコウ　ＡＢＣ１２３")
+}
+
+pub fn hankaku_registration() -> ConverterRegistration {
+    ConverterRegistration::new(
+        "半角変換",
+        "英数字とカタカナを半角に変換します",
+        || {
+            Box::new(WidthConverter {
+                direction: WidthDirection::ToHalf,
+            })
+        },
+    )
+    .trigger('w')
+    .alias("han")
+    .example("ガッコウ　ＡＢＣ１２３", "ｶﾞｯｺｳ ABC123")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_width() {
+        let cases = [
+            ("ABC xyz!", WidthDirection::ToFull, "ＡＢＣ　ｘｙｚ！"),
+            ("ＡＢＣ　ｘｙｚ！", WidthDirection::ToHalf, "ABC xyz!"),
+            ("0123456789", WidthDirection::ToFull, "０１２３４５６７８９"),
+            ("０１２３４５６７８９", WidthDirection::ToHalf, "0123456789"),
+            ("ｶﾞｯｺｳ", WidthDirection::ToFull, "ガッコウ"),
+            ("ガッコウ", WidthDirection::ToHalf, "ｶﾞｯｺｳ"),
+            ("ﾊﾟﾝ", WidthDirection::ToFull, "パン"),
+            ("パン", WidthDirection::ToHalf, "ﾊﾟﾝ"),
+            ("ｳﾞｨｰﾅｽ", WidthDirection::ToFull, "ヴィーナス"),
+            ("｢ﾃｽﾄ｣､ﾃｽﾄ｡", WidthDirection::ToFull, "「テスト」、テスト。"),
+            (
+                "「テスト」、テスト・テスト。",
+                WidthDirection::ToHalf,
+                "「ﾃｽﾄ」、ﾃｽﾄ・ﾃｽﾄ。",
+            ),
+            ("ひらがな漢字", WidthDirection::ToHalf, "ひらがな漢字"),
+            ("ヰヱ", WidthDirection::ToHalf, "ヰヱ"),
+        ];
+        for (input, direction, expected) in cases {
+            let converted = match direction {
+                WidthDirection::ToFull => to_full_width(input),
+                WidthDirection::ToHalf => to_half_width(input),
+            };
+            assert_eq!(converted, expected, "{:?} {}", direction, input);
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for half in ["ｶﾞｯｺｳ ABC123", "ﾊﾟﾋﾟﾌﾟﾍﾟﾎﾟ", "ｳﾞｧｲｵﾘﾝ ~{}"]
+        {
+            assert_eq!(to_half_width(&to_full_width(half)), half);
+        }
+        for full in [
+            "ガッコウ　ＡＢＣ１２３",
+            "バビブベボ",
+            "「カタカナ」、ひらがな。",
+        ] {
+            assert_eq!(to_full_width(&to_half_width(full)), full);
+        }
+    }
+}
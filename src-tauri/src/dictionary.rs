@@ -1,19 +1,19 @@
 use std::{
     fs::File,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use anyhow::Result;
-use regex::Regex;
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
 use tauri::State;
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     config::Config,
     converter::converter::{get_custom_converter, Converter},
-    AppState,
+    dictionary_template, AppState,
 };
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -29,6 +29,18 @@ impl Default for ConversionMethod {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum DictionaryStage {
+    PreConversion,
+    PostConversion,
+}
+
+impl Default for DictionaryStage {
+    fn default() -> Self {
+        Self::PreConversion
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DictionaryEntry {
     pub input: String,
@@ -36,6 +48,14 @@ pub struct DictionaryEntry {
     pub output: Option<String>,
     pub use_regex: bool,
     pub priority: i32,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default)]
+    pub whole_message: bool,
+    #[serde(default)]
+    pub stage: DictionaryStage,
 }
 
 impl Default for DictionaryEntry {
@@ -46,18 +66,31 @@ impl Default for DictionaryEntry {
             output: None,
             use_regex: false,
             priority: 0,
+            case_insensitive: false,
+            whole_word: false,
+            whole_message: false,
+            stage: DictionaryStage::PreConversion,
         }
     }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Dictionary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub entries: Vec<DictionaryEntry>,
 }
 
 impl Default for Dictionary {
     fn default() -> Self {
         Self {
+            name: None,
+            author: None,
+            version: None,
             entries: Vec::new(),
         }
     }
@@ -73,7 +106,12 @@ impl Dictionary {
             info!("Dictionary file not found, generating default");
             Self::generate_default_dictionary()?;
         }
-        let mut file = File::open(&dict_path)?;
+        Self::load_from(&dict_path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Dictionary> {
+        debug!("Loading dictionary from {:?}", path);
+        let mut file = File::open(path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
         trace!("Raw dictionary contents: {}", contents);
@@ -134,10 +172,53 @@ impl Dictionary {
         trace!("Dictionary path: {:?}", path);
         path
     }
+}
 
-    pub fn apply_conversion(&self, text: &str) -> Result<String> {
-        debug!("Applying dictionary conversions to: {}", text);
+impl DictionaryEntry {
+    pub fn needs_regex(&self) -> bool {
+        self.use_regex || self.case_insensitive || self.whole_word || self.whole_message
+    }
 
-        todo!()
+    /// Flags are written inline so the pattern also works inside a `RegexSet`
+    pub fn build_regex(&self) -> Result<Regex> {
+        let mut pattern = if self.use_regex {
+            self.input.clone()
+        } else {
+            regex::escape(&self.input)
+        };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        if self.whole_message {
+            pattern = format!(r"^(?:{})$", pattern);
+        }
+        if self.case_insensitive {
+            pattern = format!("(?i){}", pattern);
+        }
+        trace!("Dictionary pattern for '{}': {}", self.input, pattern);
+
+        Regex::new(&pattern).map_err(|e| anyhow!("Invalid regex '{}': {}", self.input, e))
+    }
+
+    pub fn convert_match(&self, matched: &str, captures: Option<&Captures>) -> Result<String> {
+        match &self.method {
+            ConversionMethod::Replace => match (&self.output, captures) {
+                // `$1` / `${name}` references are only expanded for regex entries
+                (Some(output), Some(caps)) if self.use_regex => {
+                    Ok(dictionary_template::expand(output, Some(caps)))
+                }
+                (Some(output), _) => Ok(dictionary_template::expand(output, None)),
+                (None, _) => {
+                    warn!("Replace entry '{}' has no output, keeping text", self.input);
+                    Ok(matched.to_string())
+                }
+            },
+            ConversionMethod::None => Ok(matched.to_string()),
+            ConversionMethod::Converter(c) => {
+                let converter = get_custom_converter(&c.to_string())
+                    .ok_or_else(|| anyhow!("Unknown converter '{}' for '{}'", c, self.input))?;
+                converter.convert(matched)
+            }
+        }
     }
 }
@@ -0,0 +1,231 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use encoding_rs::{SHIFT_JIS, UTF_16LE};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, trace, warn};
+
+use crate::dictionary::{ConversionMethod, Dictionary, DictionaryEntry, DictionaryStage};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum DictionaryFormat {
+    MsIme,
+    GoogleIme,
+    Atok,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportLineError {
+    pub line: usize,
+    pub content: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryImport {
+    pub entries: Vec<DictionaryEntry>,
+    pub errors: Vec<ImportLineError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryImportReport {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub errors: Vec<ImportLineError>,
+}
+
+const ATOK_HEADER: &str = "!!ATOK_TANGO_TEXT_HEADER_1";
+const MS_IME_HEADER: &str = "!Microsoft IME Dictionary Tool";
+
+impl DictionaryImport {
+    pub fn merge_into(self, dictionary: &mut Dictionary) -> DictionaryImportReport {
+        let mut imported = 0;
+        let mut duplicates = 0;
+        for entry in self.entries {
+            if dictionary
+                .entries
+                .iter()
+                .any(|e| e.input == entry.input && e.output == entry.output)
+            {
+                trace!("Skipping duplicate entry: {}", entry.input);
+                duplicates += 1;
+                continue;
+            }
+            dictionary.entries.push(entry);
+            imported += 1;
+        }
+        debug!(
+            "Merged {} entries, skipped {} duplicates",
+            imported, duplicates
+        );
+
+        DictionaryImportReport {
+            imported,
+            duplicates,
+            errors: self.errors,
+        }
+    }
+}
+
+impl DictionaryFormat {
+    pub fn import_file(&self, path: &Path) -> Result<DictionaryImport> {
+        debug!("Importing {:?} dictionary from {:?}", self, path);
+        let bytes = fs::read(path)?;
+        let import = self.parse(&decode_text(&bytes));
+        info!(
+            "Imported {} entries with {} errors",
+            import.entries.len(),
+            import.errors.len()
+        );
+        Ok(import)
+    }
+
+    pub fn export_file(&self, dictionary: &Dictionary, path: &Path) -> Result<usize> {
+        debug!("Exporting {:?} dictionary to {:?}", self, path);
+        let (text, count) = self.serialize(dictionary);
+        fs::write(path, self.encode_text(&text))?;
+        info!("Exported {} entries", count);
+        Ok(count)
+    }
+
+    pub fn parse(&self, text: &str) -> DictionaryImport {
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || self.is_comment(line) {
+                trace!("Skipping line {}: {}", index + 1, line);
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let error = |message: &str| ImportLineError {
+                line: index + 1,
+                content: line.to_string(),
+                message: message.to_string(),
+            };
+
+            if fields.len() < 2 {
+                warn!("Line {} has no tab separated word: {}", index + 1, line);
+                errors.push(error("Expected tab separated reading and word"));
+                continue;
+            }
+
+            let reading = fields[0].trim();
+            let word = fields[1].trim();
+            if reading.is_empty() {
+                errors.push(error("Reading is empty"));
+                continue;
+            }
+            if word.is_empty() {
+                errors.push(error("Word is empty"));
+                continue;
+            }
+
+            entries.push(DictionaryEntry {
+                input: reading.to_string(),
+                method: ConversionMethod::Replace,
+                output: Some(word.to_string()),
+                ..Default::default()
+            });
+        }
+
+        DictionaryImport { entries, errors }
+    }
+
+    /// Returns the serialized text and the number of exported entries.
+    /// Only plain `Replace` entries have an equivalent in IME user dictionaries.
+    pub fn serialize(&self, dictionary: &Dictionary) -> (String, usize) {
+        let mut lines = Vec::new();
+        match self {
+            Self::MsIme => {
+                lines.push(MS_IME_HEADER.to_string());
+                lines.push("!Format:WORDLIST".to_string());
+            }
+            Self::Atok => lines.push(ATOK_HEADER.to_string()),
+            Self::GoogleIme => {}
+        }
+
+        let mut count = 0;
+        for entry in &dictionary.entries {
+            let output = match (&entry.method, &entry.output) {
+                (ConversionMethod::Replace, Some(output))
+                    if !entry.use_regex && entry.stage == DictionaryStage::PreConversion =>
+                {
+                    output
+                }
+                _ => {
+                    trace!(
+                        "Skipping entry not representable as a word: {}",
+                        entry.input
+                    );
+                    continue;
+                }
+            };
+            if entry.input.contains(['\t', '\n']) || output.contains(['\t', '\n']) {
+                warn!("Skipping entry containing tab or newline: {}", entry.input);
+                continue;
+            }
+            lines.push(format!(
+                "{}\t{}\t{}",
+                entry.input,
+                output,
+                self.default_part_of_speech()
+            ));
+            count += 1;
+        }
+
+        let line_ending = match self {
+            Self::GoogleIme => "\n",
+            Self::MsIme | Self::Atok => "\r\n",
+        };
+        let mut text = lines.join(line_ending);
+        text.push_str(line_ending);
+        (text, count)
+    }
+
+    fn is_comment(&self, line: &str) -> bool {
+        match self {
+            Self::MsIme | Self::Atok => line.starts_with('!'),
+            Self::GoogleIme => line.starts_with('#') || line.starts_with('!'),
+        }
+    }
+
+    fn default_part_of_speech(&self) -> &'static str {
+        match self {
+            Self::MsIme | Self::GoogleIme => "名詞",
+            Self::Atok => "名詞*",
+        }
+    }
+
+    fn encode_text(&self, text: &str) -> Vec<u8> {
+        match self {
+            Self::GoogleIme => text.as_bytes().to_vec(),
+            Self::MsIme | Self::Atok => {
+                let mut bytes = vec![0xFF, 0xFE];
+                for unit in text.encode_utf16() {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// Detects UTF-16LE (BOM), UTF-8 and Shift_JIS, in that order
+pub fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        debug!("Detected UTF-16LE with BOM");
+        return UTF_16LE.decode_with_bom_removal(bytes).0.into_owned();
+    }
+
+    let without_bom = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    if let Ok(text) = std::str::from_utf8(without_bom) {
+        debug!("Detected UTF-8");
+        return text.to_string();
+    }
+
+    debug!("Falling back to Shift_JIS");
+    SHIFT_JIS.decode(bytes).0.into_owned()
+}
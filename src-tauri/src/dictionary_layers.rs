@@ -0,0 +1,342 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{config::Config, dictionary::Dictionary, dictionary_validation};
+
+/// File name used for the personal dictionary edited in the Dictionary tab
+pub const PERSONAL_LAYER: &str = "dictionary.yaml";
+
+/// A parsed layer file and the entries that failed validation
+#[derive(Debug, Clone)]
+struct LayerFile {
+    modified: SystemTime,
+    dictionary: Dictionary,
+    invalid_entries: BTreeSet<usize>,
+}
+
+/// Layer files by path, re-read only when their modification time changes
+static LAYER_FILES: Lazy<Mutex<HashMap<PathBuf, LayerFile>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DictionaryLayer {
+    /// File name in the dictionaries directory, or an absolute path to a file
+    /// elsewhere such as a team dictionary in a synced folder
+    pub file: String,
+    #[serde(default = "bool_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryLayerInfo {
+    pub file: String,
+    pub enabled: bool,
+    pub personal: bool,
+    /// Added from outside the dictionaries directory, so it can be removed
+    pub external: bool,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub version: Option<String>,
+    pub entry_count: usize,
+    /// Entries skipped because they have validation errors
+    pub invalid_entry_count: usize,
+    pub error: Option<String>,
+}
+
+/// Order and enabled state of every dictionary file
+///
+/// The state is kept in its own file so that toggling a shared dictionary
+/// never rewrites the shared file itself. Earlier layers win ties between
+/// entries with the same priority.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DictionaryLayers {
+    #[serde(default)]
+    pub layers: Vec<DictionaryLayer>,
+}
+
+#[inline]
+fn bool_true() -> bool {
+    true
+}
+
+impl DictionaryLayers {
+    pub fn load() -> Result<DictionaryLayers> {
+        debug!("Loading dictionary layers");
+        std::fs::create_dir_all(Self::get_dir())?;
+
+        let path = Self::get_path();
+        let mut layers = if path.exists() {
+            let mut file = File::open(&path)?;
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)?;
+            trace!("Raw dictionary layers contents: {}", contents);
+            serde_yaml::from_str(&contents)?
+        } else {
+            info!("Dictionary layers file not found, using defaults");
+            DictionaryLayers::default()
+        };
+        layers.sync_with_dir()?;
+        debug!("Loaded {} dictionary layers", layers.layers.len());
+        Ok(layers)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        debug!("Saving dictionary layers");
+        let mut file = File::create(Self::get_path())?;
+        let yaml = serde_yaml::to_string(&self)?;
+        trace!("Dictionary layers to be saved: {}", yaml);
+        file.write_all(yaml.as_bytes())?;
+        file.flush()?;
+        info!("Dictionary layers saved successfully");
+        Ok(())
+    }
+
+    pub fn get_path() -> PathBuf {
+        let path = Config::get_path().join("dictionary_layers.yaml");
+        trace!("Dictionary layers path: {:?}", path);
+        path
+    }
+
+    pub fn get_dir() -> PathBuf {
+        let path = Config::get_path().join("dictionaries");
+        trace!("Dictionary layers directory: {:?}", path);
+        path
+    }
+
+    /// Adds newly found files (enabled) and forgets files that were removed
+    fn sync_with_dir(&mut self) -> Result<()> {
+        let mut files = std::fs::read_dir(Self::get_dir())?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("yaml") | Some("yml")
+                    )
+            })
+            .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+            .filter(|file| file != PERSONAL_LAYER)
+            .collect::<Vec<_>>();
+        files.sort();
+
+        self.layers.retain(|layer| {
+            layer.file == PERSONAL_LAYER || is_external(&layer.file) || files.contains(&layer.file)
+        });
+        if !self.layers.iter().any(|layer| layer.file == PERSONAL_LAYER) {
+            self.layers.insert(
+                0,
+                DictionaryLayer {
+                    file: PERSONAL_LAYER.to_string(),
+                    enabled: true,
+                },
+            );
+        }
+        for file in files {
+            if !self.layers.iter().any(|layer| layer.file == file) {
+                info!("Found new dictionary layer: {}", file);
+                self.layers.push(DictionaryLayer {
+                    file,
+                    enabled: true,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a layer file, reusing the parsed file while it is unchanged
+    fn load_layer(&self, file: &str) -> Result<LayerFile> {
+        let path = if file == PERSONAL_LAYER {
+            if !Dictionary::get_path().exists() {
+                Dictionary::load()?;
+            }
+            Dictionary::get_path()
+        } else {
+            // An absolute `file` replaces the directory when joined
+            Self::get_dir().join(file)
+        };
+        let modified = std::fs::metadata(&path)?.modified()?;
+
+        let mut layer_files = LAYER_FILES.lock().unwrap();
+        if let Some(layer_file) = layer_files.get(&path) {
+            if layer_file.modified == modified {
+                trace!("Using cached dictionary layer {:?}", path);
+                return Ok(layer_file.clone());
+            }
+        }
+
+        let dictionary = Dictionary::load_from(&path)?;
+        let invalid_entries = dictionary_validation::validate(&dictionary)
+            .into_iter()
+            .filter(|d| d.is_error())
+            .map(|d| {
+                warn!(
+                    "Dictionary layer {} entry #{}: {}",
+                    file,
+                    d.index + 1,
+                    d.message
+                );
+                d.index
+            })
+            .collect::<BTreeSet<_>>();
+        let layer_file = LayerFile {
+            modified,
+            dictionary,
+            invalid_entries,
+        };
+        layer_files.insert(path, layer_file.clone());
+        Ok(layer_file)
+    }
+
+    pub fn list(&self) -> Vec<DictionaryLayerInfo> {
+        self.layers
+            .iter()
+            .map(|layer| {
+                let (layer_file, error) = match self.load_layer(&layer.file) {
+                    Ok(layer_file) => (Some(layer_file), None),
+                    Err(e) => {
+                        warn!("Failed to load dictionary layer {}: {}", layer.file, e);
+                        (None, Some(e.to_string()))
+                    }
+                };
+                let dictionary = layer_file.as_ref().map(|f| &f.dictionary);
+                DictionaryLayerInfo {
+                    file: layer.file.clone(),
+                    enabled: layer.enabled,
+                    personal: layer.file == PERSONAL_LAYER,
+                    external: is_external(&layer.file),
+                    name: dictionary.and_then(|d| d.name.clone()),
+                    author: dictionary.and_then(|d| d.author.clone()),
+                    version: dictionary.and_then(|d| d.version.clone()),
+                    entry_count: dictionary.map(|d| d.entries.len()).unwrap_or_default(),
+                    invalid_entry_count: layer_file
+                        .as_ref()
+                        .map(|f| f.invalid_entries.len())
+                        .unwrap_or_default(),
+                    error,
+                }
+            })
+            .collect()
+    }
+
+    /// Adds a dictionary file outside the dictionaries directory
+    pub fn add_external(&mut self, path: &Path) -> Result<()> {
+        if !path.is_absolute() || !path.is_file() {
+            return Err(anyhow!("Not a dictionary file: {:?}", path));
+        }
+        let file = path.to_string_lossy().to_string();
+        if self.layers.iter().any(|layer| layer.file == file) {
+            return Err(anyhow!("Dictionary layer already added: {}", file));
+        }
+        self.load_layer(&file)?;
+        info!("Added external dictionary layer: {}", file);
+        self.layers.push(DictionaryLayer {
+            file,
+            enabled: true,
+        });
+        Ok(())
+    }
+
+    /// Forgets an external layer; files in the dictionaries directory are
+    /// removed by deleting the file instead
+    pub fn remove_external(&mut self, file: &str) -> Result<()> {
+        if !is_external(file) {
+            return Err(anyhow!("Not an external dictionary layer: {}", file));
+        }
+        let count = self.layers.len();
+        self.layers.retain(|layer| layer.file != file);
+        if self.layers.len() == count {
+            return Err(anyhow!("Unknown dictionary layer: {}", file));
+        }
+        info!("Removed external dictionary layer: {}", file);
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, file: &str, enabled: bool) -> Result<()> {
+        let layer = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.file == file)
+            .ok_or_else(|| anyhow!("Unknown dictionary layer: {}", file))?;
+        layer.enabled = enabled;
+        info!("Dictionary layer {} enabled: {}", file, enabled);
+        Ok(())
+    }
+
+    /// Reorders layers; `files` must name every known layer exactly once
+    pub fn reorder(&mut self, files: &[String]) -> Result<()> {
+        if files.len() != self.layers.len() {
+            return Err(anyhow!(
+                "Expected {} dictionary layers, got {}",
+                self.layers.len(),
+                files.len()
+            ));
+        }
+        let mut reordered = Vec::with_capacity(files.len());
+        for file in files {
+            let layer = self
+                .layers
+                .iter()
+                .find(|layer| &layer.file == file)
+                .ok_or_else(|| anyhow!("Unknown dictionary layer: {}", file))?;
+            if reordered.iter().any(|l: &DictionaryLayer| &l.file == file) {
+                return Err(anyhow!("Duplicate dictionary layer: {}", file));
+            }
+            reordered.push(layer.clone());
+        }
+        self.layers = reordered;
+        info!("Dictionary layers reordered: {:?}", files);
+        Ok(())
+    }
+
+    /// Concatenates the entries of every enabled layer in layer order
+    ///
+    /// # Arguments
+    /// * `personal` - In-memory personal dictionary, which may be newer than the file
+    pub fn merge(&self, personal: &Dictionary) -> Dictionary {
+        let mut merged = Dictionary::default();
+        for layer in self.layers.iter().filter(|layer| layer.enabled) {
+            if layer.file == PERSONAL_LAYER {
+                merged.entries.extend(personal.entries.iter().cloned());
+                continue;
+            }
+            match self.load_layer(&layer.file) {
+                Ok(layer_file) => {
+                    debug!(
+                        "Merging dictionary layer {} with {} entries, skipping {} invalid",
+                        layer.file,
+                        layer_file.dictionary.entries.len(),
+                        layer_file.invalid_entries.len()
+                    );
+                    merged.entries.extend(
+                        layer_file
+                            .dictionary
+                            .entries
+                            .into_iter()
+                            .enumerate()
+                            .filter(|(index, _)| !layer_file.invalid_entries.contains(index))
+                            .map(|(_, entry)| entry),
+                    );
+                }
+                Err(e) => error!("Failed to load dictionary layer {}: {}", layer.file, e),
+            }
+        }
+        debug!("Merged dictionary has {} entries", merged.entries.len());
+        merged
+    }
+}
+
+fn is_external(file: &str) -> bool {
+    Path::new(file).is_absolute()
+}
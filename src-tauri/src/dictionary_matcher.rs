@@ -0,0 +1,517 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use anyhow::Result;
+use regex::{Captures, Regex, RegexSet, RegexSetBuilder};
+use tracing::{debug, error, trace, warn};
+
+use crate::{
+    azookey::user_dictionary::UserDictionary,
+    dictionary::{Dictionary, DictionaryEntry, DictionaryStage},
+};
+
+/// A dictionary compiled once per `load_dictionary` / `save_dictionary`
+pub struct CompiledDictionary {
+    pub pre_conversion: DictionaryMatcher,
+    pub post_conversion: DictionaryMatcher,
+    pub user_dictionary: UserDictionary,
+}
+
+impl CompiledDictionary {
+    pub fn new(dictionary: &Dictionary) -> Self {
+        Self {
+            pre_conversion: DictionaryMatcher::new(dictionary, DictionaryStage::PreConversion),
+            post_conversion: DictionaryMatcher::new(dictionary, DictionaryStage::PostConversion),
+            user_dictionary: UserDictionary::from_dictionary(dictionary),
+        }
+    }
+
+    pub fn matcher(&self, stage: DictionaryStage) -> &DictionaryMatcher {
+        match stage {
+            DictionaryStage::PreConversion => &self.pre_conversion,
+            DictionaryStage::PostConversion => &self.post_conversion,
+        }
+    }
+}
+
+const REGEX_SET_DFA_SIZE_LIMIT: usize = 64 * 1024 * 1024;
+
+struct RegexEntry {
+    entry: DictionaryEntry,
+    regex: Regex,
+}
+
+struct Candidate<'t> {
+    start: usize,
+    end: usize,
+    priority: i32,
+    /// Position in the original dictionary, used to keep file order on ties
+    order: usize,
+    entry: &'t DictionaryEntry,
+    captures: Option<Captures<'t>>,
+}
+
+/// Part of a text after dictionary matching
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchedSegment {
+    /// Text that no entry matched
+    Text(String),
+    /// Output of an entry, which the conversion engines pass through unchanged
+    Replaced(String),
+}
+
+impl MatchedSegment {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Text(text) | Self::Replaced(text) => text,
+        }
+    }
+}
+
+/// Byte ranges of the entry outputs in the joined text of `segments`
+pub fn replaced_spans(segments: &[MatchedSegment]) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut position = 0;
+    for segment in segments {
+        let end = position + segment.as_str().len();
+        if matches!(segment, MatchedSegment::Replaced(_)) {
+            spans.push(position..end);
+        }
+        position = end;
+    }
+    spans
+}
+
+/// Matches every dictionary entry against a text in a single pass
+///
+/// Literal inputs go into one Aho-Corasick automaton and regex inputs into a
+/// `RegexSet`, so the cost of a conversion depends on the text and the number
+/// of entries that actually match, not on the size of the dictionary.
+pub struct DictionaryMatcher {
+    literals: Vec<(usize, DictionaryEntry)>,
+    automaton: Option<AhoCorasick>,
+    regexes: Vec<(usize, RegexEntry)>,
+    regex_set: Option<RegexSet>,
+}
+
+impl DictionaryMatcher {
+    pub fn new(dictionary: &Dictionary, stage: DictionaryStage) -> Self {
+        debug!(
+            "Compiling {:?} dictionary matcher for {} entries",
+            stage,
+            dictionary.entries.len()
+        );
+        let mut literals = Vec::new();
+        let mut regexes = Vec::new();
+
+        for (order, entry) in dictionary.entries.iter().enumerate() {
+            if entry.input.is_empty() || entry.stage != stage {
+                continue;
+            }
+            if !entry.needs_regex() {
+                literals.push((order, entry.clone()));
+                continue;
+            }
+            match entry.build_regex() {
+                Ok(regex) => regexes.push((
+                    order,
+                    RegexEntry {
+                        entry: entry.clone(),
+                        regex,
+                    },
+                )),
+                Err(e) => warn!("Skipping dictionary entry: {}", e),
+            }
+        }
+
+        let automaton = if literals.is_empty() {
+            None
+        } else {
+            AhoCorasickBuilder::new()
+                .match_kind(MatchKind::Standard)
+                .build(literals.iter().map(|(_, entry)| &entry.input))
+                .map_err(|e| error!("Failed to build literal automaton: {}", e))
+                .ok()
+        };
+
+        let regex_set = if regexes.is_empty() {
+            None
+        } else {
+            // The default lazy DFA cache is too small for hundreds of patterns and
+            // silently falls back to the much slower NFA
+            RegexSetBuilder::new(regexes.iter().map(|(_, r)| r.regex.as_str()))
+                .dfa_size_limit(REGEX_SET_DFA_SIZE_LIMIT)
+                .build()
+                .map_err(|e| {
+                    warn!(
+                        "Failed to build regex set, matching each regex on its own: {}",
+                        e
+                    )
+                })
+                .ok()
+        };
+
+        debug!(
+            "Dictionary matcher compiled: {} literals, {} regexes",
+            literals.len(),
+            regexes.len()
+        );
+        Self {
+            literals,
+            automaton,
+            regexes,
+            regex_set,
+        }
+    }
+
+    /// Rewrites every matched span of `text`
+    pub fn apply(&self, text: &str) -> String {
+        self.segments(text)
+            .iter()
+            .map(MatchedSegment::as_str)
+            .collect()
+    }
+
+    /// Splits `text` into unmatched text and entry outputs
+    ///
+    /// Overlapping matches are resolved by priority first, then leftmost,
+    /// then longest, then dictionary order.
+    /// An entry whose conversion fails is skipped, so its span is left to the
+    /// other entries or kept as is.
+    pub fn segments(&self, text: &str) -> Vec<MatchedSegment> {
+        trace!("Applying dictionary matcher to: {}", text);
+        let mut candidates = self.find_candidates(text);
+        candidates.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.start.cmp(&b.start))
+                .then((b.end - b.start).cmp(&(a.end - a.start)))
+                .then(a.order.cmp(&b.order))
+        });
+
+        // Claimed spans never overlap, so only the last one starting before
+        // `end` can overlap a new span
+        let mut claimed: BTreeMap<usize, (usize, String)> = BTreeMap::new();
+        for candidate in candidates {
+            let (start, end) = (candidate.start, candidate.end);
+            let overlaps = claimed
+                .range(..end)
+                .next_back()
+                .is_some_and(|(_, (claimed_end, _))| *claimed_end > start);
+            if overlaps {
+                trace!(
+                    "Skipping overlapping match for '{}' at {}..{}",
+                    candidate.entry.input,
+                    start,
+                    end
+                );
+                continue;
+            }
+            let replacement = match candidate
+                .entry
+                .convert_match(&text[start..end], candidate.captures.as_ref())
+            {
+                Ok(replacement) => replacement,
+                Err(e) => {
+                    warn!(
+                        "Skipping dictionary entry '{}' at {}..{}: {}",
+                        candidate.entry.input, start, end, e
+                    );
+                    continue;
+                }
+            };
+            trace!(
+                "Dictionary match '{}' at {}..{}: {} -> {}",
+                candidate.entry.input,
+                start,
+                end,
+                &text[start..end],
+                replacement
+            );
+            claimed.insert(start, (end, replacement));
+        }
+
+        let mut segments = Vec::with_capacity(claimed.len() * 2 + 1);
+        let mut last_end = 0;
+        for (start, (end, replacement)) in claimed {
+            if start > last_end {
+                segments.push(MatchedSegment::Text(text[last_end..start].to_string()));
+            }
+            if !replacement.is_empty() {
+                segments.push(MatchedSegment::Replaced(replacement));
+            }
+            last_end = end;
+        }
+        if last_end < text.len() {
+            segments.push(MatchedSegment::Text(text[last_end..].to_string()));
+        }
+        segments
+    }
+
+    fn find_candidates<'t>(&'t self, text: &'t str) -> Vec<Candidate<'t>> {
+        let mut candidates = Vec::new();
+
+        if let Some(automaton) = &self.automaton {
+            for m in automaton.find_overlapping_iter(text) {
+                let (order, entry) = &self.literals[m.pattern().as_usize()];
+                candidates.push(Candidate {
+                    start: m.start(),
+                    end: m.end(),
+                    priority: entry.priority,
+                    order: *order,
+                    entry,
+                    captures: None,
+                });
+            }
+        }
+
+        let matched_regexes = match &self.regex_set {
+            Some(regex_set) => regex_set.matches(text).into_iter().collect(),
+            None => (0..self.regexes.len()).collect::<Vec<_>>(),
+        };
+        for index in matched_regexes {
+            let (order, regex_entry) = &self.regexes[index];
+            for captures in regex_entry.regex.captures_iter(text) {
+                let Some(m) = captures.get(0) else {
+                    continue;
+                };
+                if m.is_empty() {
+                    continue;
+                }
+                candidates.push(Candidate {
+                    start: m.start(),
+                    end: m.end(),
+                    priority: regex_entry.entry.priority,
+                    order: *order,
+                    entry: &regex_entry.entry,
+                    captures: Some(captures),
+                });
+            }
+        }
+
+        trace!("Found {} dictionary candidates", candidates.len());
+        candidates
+    }
+}
+
+/// Converts `text` piece by piece, passing the `protected` byte ranges
+/// through unchanged
+///
+/// Used by the AzooKey and TSF engines, which take the whole message, so that
+/// pre-conversion dictionary outputs such as `VRChat` are not converted again.
+/// The ranges come from `replaced_spans` and refer to the full message, of
+/// which `text` is the part starting at byte `offset`; ranges before it are
+/// ignored and ranges that cross it are cut.
+pub fn convert_around(
+    text: &str,
+    offset: usize,
+    protected: &[Range<usize>],
+    mut convert: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for span in protected {
+        let start = span.start.saturating_sub(offset).max(last_end);
+        let end = span.end.saturating_sub(offset).min(text.len());
+        if start >= end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            continue;
+        }
+        if start > last_end {
+            result.push_str(&convert(&text[last_end..start])?);
+        }
+        trace!("Keeping protected span: {}", &text[start..end]);
+        result.push_str(&text[start..end]);
+        last_end = end;
+    }
+    if last_end < text.len() {
+        result.push_str(&convert(&text[last_end..])?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::dictionary::ConversionMethod;
+
+    fn replace(input: &str, output: &str, priority: i32) -> DictionaryEntry {
+        DictionaryEntry {
+            input: input.to_string(),
+            method: ConversionMethod::Replace,
+            output: Some(output.to_string()),
+            priority,
+            ..Default::default()
+        }
+    }
+
+    fn apply(entries: Vec<DictionaryEntry>, text: &str) -> String {
+        let dictionary = Dictionary {
+            entries,
+            ..Default::default()
+        };
+        DictionaryMatcher::new(&dictionary, DictionaryStage::PreConversion).apply(text)
+    }
+
+    #[test]
+    fn higher_priority_wins() {
+        let entries = vec![replace("abc", "X", 0), replace("bcd", "Y", 1)];
+        assert_eq!(apply(entries, "abcd"), "aY");
+    }
+
+    #[test]
+    fn overlapping_literals_resolve_leftmost_longest() {
+        let entries = vec![
+            replace("ab", "1", 0),
+            replace("abc", "2", 0),
+            replace("bcd", "3", 0),
+        ];
+        assert_eq!(apply(entries, "abcd"), "2d");
+    }
+
+    #[test]
+    fn regex_entry_matches() {
+        let entries = vec![DictionaryEntry {
+            use_regex: true,
+            ..replace(r"(\d+)円", "${1} yen", 0)
+        }];
+        assert_eq!(apply(entries, "100円です"), "100 yenです");
+    }
+
+    #[test]
+    fn converter_entry_routes_through_converter() {
+        let entries = vec![DictionaryEntry {
+            input: "vr".to_string(),
+            method: ConversionMethod::Converter('z'),
+            ..Default::default()
+        }];
+        assert_eq!(apply(entries, "vrc"), "ｖｒc");
+    }
+
+    #[test]
+    fn failing_entry_is_skipped() {
+        let entries = vec![
+            DictionaryEntry {
+                input: "a".to_string(),
+                method: ConversionMethod::Converter('?'),
+                ..Default::default()
+            },
+            replace("b", "B", 0),
+        ];
+        assert_eq!(apply(entries, "ab"), "aB");
+    }
+
+    #[test]
+    fn invalid_regex_does_not_disable_others() {
+        let entries = vec![
+            DictionaryEntry {
+                use_regex: true,
+                ..replace("(", "?", 0)
+            },
+            DictionaryEntry {
+                use_regex: true,
+                ..replace(r"\d+", "N", 0)
+            },
+        ];
+        assert_eq!(apply(entries, "a1b22"), "aNbN");
+    }
+
+    /// Average time of `apply` over a text where a few entries match
+    fn apply_latency(entry_count: usize) -> Duration {
+        let mut entries = (0..entry_count)
+            .map(|i| replace(&format!("word{:05}", i), "W", 0))
+            .collect::<Vec<_>>();
+        entries.push(DictionaryEntry {
+            use_regex: true,
+            ..replace(r"(\d+)円", "${1} yen", 0)
+        });
+        let dictionary = Dictionary {
+            entries,
+            ..Default::default()
+        };
+        let matcher = DictionaryMatcher::new(&dictionary, DictionaryStage::PreConversion);
+        let text = "word00001 と word00050 を 100円 で買った、と言っていた".repeat(4);
+
+        let iterations = 200;
+        let started = Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(matcher.apply(&text));
+        }
+        started.elapsed() / iterations
+    }
+
+    /// Timing based, so it is run on demand with `cargo test -- --ignored`
+    #[test]
+    #[ignore = "depends on wall-clock timing"]
+    fn latency_does_not_grow_with_dictionary_size() {
+        apply_latency(100);
+        let small = apply_latency(100);
+        let large = apply_latency(20_000);
+        // A per-entry scan would be about 200 times slower; allow for timer noise
+        assert!(
+            large < small * 5 + Duration::from_micros(200),
+            "100 entries: {:?}, 20000 entries: {:?}",
+            small,
+            large
+        );
+    }
+
+    #[test]
+    fn segments_mark_replaced_spans() {
+        let dictionary = Dictionary {
+            entries: vec![replace("vrc", "VRChat", 0)],
+            ..Default::default()
+        };
+        let matcher = DictionaryMatcher::new(&dictionary, DictionaryStage::PreConversion);
+        assert_eq!(
+            matcher.segments("vrc de asobu"),
+            vec![
+                MatchedSegment::Replaced("VRChat".to_string()),
+                MatchedSegment::Text(" de asobu".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn convert_around_keeps_protected_spans() {
+        let converted = convert_around("VRChat de asobu w", 0, &[0..6, 16..17], |text| {
+            Ok(text.to_uppercase())
+        })
+        .unwrap();
+        assert_eq!(converted, "VRChat DE ASOBU w");
+    }
+
+    #[test]
+    fn convert_around_protects_only_matched_occurrences() {
+        let dictionary = Dictionary {
+            entries: vec![replace("lol", "w", 0)],
+            ..Default::default()
+        };
+        let matcher = DictionaryMatcher::new(&dictionary, DictionaryStage::PreConversion);
+        let segments = matcher.segments("watashi lol wa");
+        let text = segments
+            .iter()
+            .map(MatchedSegment::as_str)
+            .collect::<String>();
+        let spans = replaced_spans(&segments);
+        assert_eq!(spans, vec![8..9]);
+
+        let converted = convert_around(&text, 0, &spans, |text| Ok(text.to_uppercase())).unwrap();
+        assert_eq!(converted, "WATASHI w WA");
+    }
+
+    #[test]
+    fn convert_around_rebases_spans_on_the_offset() {
+        // Only "de w wa" is converted; the span of "w" is 10..11 in the full text
+        let converted =
+            convert_around(
+                "de w wa",
+                7,
+                &[2..4, 10..11],
+                |text| Ok(text.to_uppercase()),
+            )
+            .unwrap();
+        assert_eq!(converted, "DE w WA");
+    }
+}
@@ -0,0 +1,303 @@
+use std::{collections::HashMap, fmt::Write, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use chrono::{format::Item, format::StrftimeItems, DateTime, Local, TimeZone};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use regex::Captures;
+use tracing::{debug, trace, warn};
+
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+const DEFAULT_DATE_FORMAT: &str = "%Y/%m/%d";
+
+#[derive(Debug, Default)]
+struct TemplateState {
+    last_sent: String,
+    counters: HashMap<String, u64>,
+}
+
+static TEMPLATE_STATE: Lazy<Mutex<TemplateState>> =
+    Lazy::new(|| Mutex::new(TemplateState::default()));
+
+/// Records the text most recently sent, used by `{last_sent}`
+pub fn set_last_sent(text: &str) {
+    trace!("Last sent text: {}", text);
+    TEMPLATE_STATE.lock().unwrap().last_sent = text.to_string();
+}
+
+enum Segment<'t> {
+    Literal(&'t str),
+    Placeholder { name: &'t str, arg: Option<&'t str> },
+}
+
+/// Splits a template into literal text and `{name}` / `{name:arg}` placeholders.
+/// `{{` and `}}` produce literal braces. With `capture_refs`, `${name}` is kept
+/// as literal text for the regex to expand.
+fn parse(template: &str, capture_refs: bool) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        if pos > 0 {
+            segments.push(Segment::Literal(&rest[..pos]));
+        }
+        let tail = &rest[pos..];
+        // `$$` is an escaped dollar sign, so only an odd run starts a reference
+        let dollars = pos - rest[..pos].trim_end_matches('$').len();
+        if capture_refs && tail.starts_with('{') && dollars % 2 == 1 {
+            if let Some(end) = tail.find('}') {
+                segments.push(Segment::Literal(&tail[..=end]));
+                rest = &tail[end + 1..];
+                continue;
+            }
+        }
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            segments.push(Segment::Literal(&tail[..1]));
+            rest = &tail[2..];
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix('}') {
+            segments.push(Segment::Literal("}"));
+            rest = after;
+            continue;
+        }
+        match tail.find('}') {
+            Some(end) => {
+                let inner = &tail[1..end];
+                let (name, arg) = match inner.split_once(':') {
+                    Some((name, arg)) => (name, Some(arg)),
+                    None => (inner, None),
+                };
+                segments.push(Segment::Placeholder { name, arg });
+                rest = &tail[end + 1..];
+            }
+            None => {
+                segments.push(Segment::Literal(tail));
+                rest = "";
+            }
+        }
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest));
+    }
+    segments
+}
+
+/// Returns true if the text contains at least one placeholder
+pub fn has_placeholders(template: &str) -> bool {
+    parse(template, false)
+        .iter()
+        .any(|segment| matches!(segment, Segment::Placeholder { .. }))
+}
+
+/// Expands every placeholder in a dictionary output and substitutes the regex
+/// `captures` into the text around them
+///
+/// Captured text is inserted as it is, so a chat message containing
+/// `{random:a|b}` is never evaluated. An output without placeholders is
+/// returned unchanged, `{{` included. Unknown or invalid placeholders are kept
+/// as written so that a typo shows up in the chatbox instead of silently
+/// disappearing.
+pub fn expand(template: &str, captures: Option<&Captures>) -> String {
+    expand_at(template, captures, Local::now())
+}
+
+fn expand_at(template: &str, captures: Option<&Captures>, now: DateTime<Local>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let substitute = |text: &str, result: &mut String| match captures {
+        Some(caps) => caps.expand(text, result),
+        None => result.push_str(text),
+    };
+
+    let segments = parse(template, captures.is_some());
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, Segment::Placeholder { .. }))
+    {
+        substitute(template, &mut result);
+        return result;
+    }
+
+    // Adjacent literals are substituted together so that `$1` is never split
+    let mut literal = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => literal.push_str(text),
+            Segment::Placeholder { name, arg } => {
+                substitute(&literal, &mut result);
+                literal.clear();
+                match expand_placeholder(name, arg, now) {
+                    Ok(value) => result.push_str(&value),
+                    Err(e) => {
+                        warn!("Failed to expand placeholder: {}", e);
+                        result.push('{');
+                        result.push_str(name);
+                        if let Some(arg) = arg {
+                            result.push(':');
+                            result.push_str(arg);
+                        }
+                        result.push('}');
+                    }
+                }
+            }
+        }
+    }
+    substitute(&literal, &mut result);
+    if result != template {
+        debug!("Template expanded: {} -> {}", template, result);
+    }
+    result
+}
+
+fn expand_placeholder(name: &str, arg: Option<&str>, now: DateTime<Local>) -> Result<String> {
+    match name {
+        "time" => format_datetime(now, arg, DEFAULT_TIME_FORMAT),
+        "date" => format_datetime(now, arg, DEFAULT_DATE_FORMAT),
+        "last_sent" => Ok(TEMPLATE_STATE.lock().unwrap().last_sent.clone()),
+        "counter" => {
+            let name = arg.unwrap_or_default();
+            let mut state = TEMPLATE_STATE.lock().unwrap();
+            let count = state.counters.entry(name.to_string()).or_insert(0);
+            *count += 1;
+            trace!("Counter '{}' incremented to {}", name, count);
+            Ok(count.to_string())
+        }
+        "random" => {
+            let choices = arg
+                .ok_or_else(|| anyhow!("{{random}} needs choices such as {{random:a|b}}"))?
+                .split('|')
+                .collect::<Vec<_>>();
+            Ok(choices
+                .choose(&mut rand::thread_rng())
+                .unwrap_or(&"")
+                .to_string())
+        }
+        _ => Err(anyhow!("Unknown placeholder '{{{}}}'", name)),
+    }
+}
+
+/// `arg` is either a time zone name (`America/New_York`), a strftime format
+/// (`%m/%d`), or a time zone followed by a format (`America/New_York %H:%M`)
+fn format_datetime(
+    now: DateTime<Local>,
+    arg: Option<&str>,
+    default_format: &str,
+) -> Result<String> {
+    let arg = arg.unwrap_or_default().trim();
+    let (zone, format) = match arg.split_once(' ') {
+        Some((zone, format)) if zone.parse::<Tz>().is_ok() => (Some(zone), format.trim()),
+        _ if arg.parse::<Tz>().is_ok() => (Some(arg), default_format),
+        _ if arg.is_empty() => (None, default_format),
+        _ if arg.contains('%') => (None, arg),
+        _ => return Err(anyhow!("Unknown time zone '{}'", arg)),
+    };
+
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(anyhow!("Invalid date format '{}'", format));
+    }
+
+    let mut formatted = String::new();
+    match zone {
+        Some(zone) => {
+            let tz: Tz = zone.parse().map_err(|e| anyhow!("{}", e))?;
+            write!(
+                formatted,
+                "{}",
+                tz.from_utc_datetime(&now.naive_utc()).format(format)
+            )?;
+        }
+        None => write!(formatted, "{}", now.format(format))?,
+    }
+    Ok(formatted)
+}
+
+/// Checks placeholders without expanding them, for dictionary validation;
+/// `capture_refs` skips `${name}` references of regex entries
+pub fn validate(template: &str, capture_refs: bool) -> Result<()> {
+    for segment in parse(template, capture_refs) {
+        let Segment::Placeholder { name, arg } = segment else {
+            continue;
+        };
+        match name {
+            "time" => {
+                format_datetime(Local::now(), arg, DEFAULT_TIME_FORMAT)?;
+            }
+            "date" => {
+                format_datetime(Local::now(), arg, DEFAULT_DATE_FORMAT)?;
+            }
+            "last_sent" | "counter" => {}
+            "random" if arg.is_some() => {}
+            "random" => return Err(anyhow!("{{random}} needs choices such as {{random:a|b}}")),
+            _ => return Err(anyhow!("Unknown placeholder '{{{}}}'", name)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+    }
+
+    #[test]
+    fn keeps_outputs_without_placeholders() {
+        for output in ["plain", "{{x}}", "a}b", "{ unterminated", "$1 100%"] {
+            assert_eq!(expand_at(output, None, now()), output);
+        }
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let cases = [
+            ("{time}", "03:04"),
+            ("{date}", "2024/01/02"),
+            ("{date:%m/%d}", "01/02"),
+            ("{{time}} {time}", "{time} 03:04"),
+            ("{random:a}", "a"),
+            ("{unknown} {time}", "{unknown} 03:04"),
+        ];
+        for (output, expected) in cases {
+            assert_eq!(expand_at(output, None, now()), expected, "{}", output);
+        }
+    }
+
+    #[test]
+    fn inserts_captures_without_expanding_them() {
+        let regex = Regex::new(r"(?<message>.+)!").unwrap();
+        let cases = [
+            (
+                "{random:a|b}!",
+                "${message} at {time}",
+                "{random:a|b} at 03:04",
+            ),
+            ("{time}!", "$1", "{time}"),
+            ("{time}!", "${1}{{x}}", "{time}{{x}}"),
+            ("hi!", "$message{time}", "hi03:04"),
+            ("hi!", "${message}{time}", "hi03:04"),
+            ("hi!", "$${time}", "$03:04"),
+        ];
+        for (text, output, expected) in cases {
+            let captures = regex.captures(text).unwrap();
+            assert_eq!(
+                expand_at(output, Some(&captures), now()),
+                expected,
+                "{} {}",
+                text,
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn validates_placeholders() {
+        assert!(validate("${message} {time}", true).is_ok());
+        assert!(validate("${message}", false).is_err());
+        assert!(validate("{random}", false).is_err());
+        assert!(validate("{date:Mars/Olympus}", false).is_err());
+    }
+}
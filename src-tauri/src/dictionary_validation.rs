@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+
+use aho_corasick::AhoCorasick;
+use regex::{Regex, RegexSet};
+use serde::Serialize;
+use tracing::{debug, error, trace};
+
+use crate::{
+    converter::converter::get_custom_converter,
+    dictionary::{ConversionMethod, Dictionary, DictionaryEntry},
+    dictionary_template,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DiagnosticKind {
+    EmptyInput,
+    InvalidRegex,
+    UnknownConverter,
+    MissingOutput,
+    DuplicateInput,
+    Shadowed,
+    InvalidTemplate,
+}
+
+impl DiagnosticKind {
+    pub fn severity(&self) -> DiagnosticSeverity {
+        match self {
+            Self::EmptyInput
+            | Self::InvalidRegex
+            | Self::UnknownConverter
+            | Self::MissingOutput => DiagnosticSeverity::Error,
+            Self::DuplicateInput | Self::Shadowed | Self::InvalidTemplate => {
+                DiagnosticSeverity::Warning
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryDiagnostic {
+    /// Index of the entry in `Dictionary.entries`
+    pub index: usize,
+    pub kind: DiagnosticKind,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// Index of the entry that causes a duplicate or shadowing warning
+    pub related_index: Option<usize>,
+}
+
+impl DictionaryDiagnostic {
+    fn new(index: usize, kind: DiagnosticKind, message: String) -> Self {
+        Self {
+            index,
+            kind,
+            severity: kind.severity(),
+            message,
+            related_index: None,
+        }
+    }
+
+    fn related_to(mut self, index: usize) -> Self {
+        self.related_index = Some(index);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+/// Checks every entry and returns diagnostics sorted by entry index
+pub fn validate(dictionary: &Dictionary) -> Vec<DictionaryDiagnostic> {
+    debug!(
+        "Validating dictionary with {} entries",
+        dictionary.entries.len()
+    );
+    let mut diagnostics = Vec::new();
+    let mut regexes: Vec<(usize, Regex)> = Vec::new();
+
+    for (index, entry) in dictionary.entries.iter().enumerate() {
+        if entry.input.is_empty() {
+            diagnostics.push(DictionaryDiagnostic::new(
+                index,
+                DiagnosticKind::EmptyInput,
+                "Input is empty".to_string(),
+            ));
+            continue;
+        }
+
+        if entry.needs_regex() {
+            match entry.build_regex() {
+                Ok(regex) => regexes.push((index, regex)),
+                Err(e) => diagnostics.push(DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::InvalidRegex,
+                    e.to_string(),
+                )),
+            }
+        }
+
+        match &entry.method {
+            ConversionMethod::Replace if entry.output.as_deref().is_none_or(str::is_empty) => {
+                diagnostics.push(DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::MissingOutput,
+                    format!("Replace entry '{}' has no output", entry.input),
+                ));
+            }
+            ConversionMethod::Converter(c) if get_custom_converter(&c.to_string()).is_none() => {
+                diagnostics.push(DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::UnknownConverter,
+                    format!("Unknown converter '{}'", c),
+                ));
+            }
+            ConversionMethod::Replace => {
+                let output = entry.output.as_deref().unwrap_or_default();
+                if let Err(e) = dictionary_template::validate(output, entry.use_regex) {
+                    diagnostics.push(DictionaryDiagnostic::new(
+                        index,
+                        DiagnosticKind::InvalidTemplate,
+                        e.to_string(),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics.extend(find_duplicates(&dictionary.entries));
+    diagnostics.extend(find_regex_shadowing(&dictionary.entries, &regexes));
+    diagnostics.extend(find_literal_shadowing(&dictionary.entries));
+
+    diagnostics.sort_by_key(|d| d.index);
+    debug!(
+        "Dictionary validation found {} diagnostics",
+        diagnostics.len()
+    );
+    diagnostics
+}
+
+/// Returns true if `winner` is applied before `other` when both match the same span
+fn wins_over(entries: &[DictionaryEntry], winner: usize, other: usize) -> bool {
+    let (a, b) = (&entries[winner], &entries[other]);
+    a.priority > b.priority || (a.priority == b.priority && winner < other)
+}
+
+/// Entries with the same input and match flags can never both apply
+fn find_duplicates(entries: &[DictionaryEntry]) -> Vec<DictionaryDiagnostic> {
+    let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.input.is_empty() {
+            continue;
+        }
+        let key = (
+            entry.input.as_str(),
+            entry.use_regex,
+            entry.case_insensitive,
+            entry.whole_word,
+            entry.whole_message,
+            entry.stage,
+        );
+        groups.entry(key).or_default().push(index);
+    }
+
+    let mut diagnostics = Vec::new();
+    for indices in groups.values().filter(|indices| indices.len() > 1) {
+        let winner = indices
+            .iter()
+            .copied()
+            .reduce(|a, b| if wins_over(entries, a, b) { a } else { b })
+            .unwrap();
+        for &index in indices.iter().filter(|&&index| index != winner) {
+            let entry = &entries[index];
+            let diagnostic = if entry.priority == entries[winner].priority {
+                DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::DuplicateInput,
+                    format!(
+                        "Input '{}' duplicates entry #{} and is never applied",
+                        entry.input,
+                        winner + 1
+                    ),
+                )
+            } else {
+                DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::Shadowed,
+                    format!(
+                        "Input '{}' is shadowed by higher-priority entry #{}",
+                        entry.input,
+                        winner + 1
+                    ),
+                )
+            };
+            diagnostics.push(diagnostic.related_to(winner));
+        }
+    }
+    diagnostics
+}
+
+/// Literal entries whose whole input is matched by a regex entry that wins over them
+fn find_regex_shadowing(
+    entries: &[DictionaryEntry],
+    regexes: &[(usize, Regex)],
+) -> Vec<DictionaryDiagnostic> {
+    if regexes.is_empty() {
+        return Vec::new();
+    }
+    let set = match RegexSet::new(
+        regexes
+            .iter()
+            .map(|(_, regex)| format!("^(?:{})$", regex.as_str())),
+    ) {
+        Ok(set) => set,
+        Err(e) => {
+            error!("Failed to build regex set for validation: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.input.is_empty() || entry.needs_regex() {
+            continue;
+        }
+        let shadowing = set
+            .matches(&entry.input)
+            .iter()
+            .map(|i| regexes[i].0)
+            .filter(|&regex_index| entries[regex_index].stage == entry.stage)
+            .find(|&regex_index| wins_over(entries, regex_index, index));
+        if let Some(regex_index) = shadowing {
+            trace!(
+                "Entry #{} is shadowed by regex entry #{}",
+                index + 1,
+                regex_index + 1
+            );
+            diagnostics.push(
+                DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::Shadowed,
+                    format!(
+                        "Input '{}' is matched by higher-priority regex entry #{}",
+                        entry.input,
+                        regex_index + 1
+                    ),
+                )
+                .related_to(regex_index),
+            );
+        }
+    }
+    diagnostics
+}
+
+/// Literal entries inside a longer literal with a higher priority, e.g. `ji`
+/// under `jikan`, which never apply where the longer entry matches
+fn find_literal_shadowing(entries: &[DictionaryEntry]) -> Vec<DictionaryDiagnostic> {
+    let literals = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| !entry.input.is_empty() && !entry.needs_regex())
+        .collect::<Vec<_>>();
+    if literals.is_empty() {
+        return Vec::new();
+    }
+    let automaton = match AhoCorasick::new(literals.iter().map(|(_, entry)| &entry.input)) {
+        Ok(automaton) => automaton,
+        Err(e) => {
+            error!("Failed to build literal automaton for validation: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut reported = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for &(other_index, other) in &literals {
+        for m in automaton.find_overlapping_iter(&other.input) {
+            let (index, entry) = literals[m.pattern().as_usize()];
+            if entry.input.len() == other.input.len()
+                || entry.stage != other.stage
+                || entry.priority >= other.priority
+                || !reported.insert(index)
+            {
+                continue;
+            }
+            trace!(
+                "Entry #{} is inside higher-priority entry #{}",
+                index + 1,
+                other_index + 1
+            );
+            diagnostics.push(
+                DictionaryDiagnostic::new(
+                    index,
+                    DiagnosticKind::Shadowed,
+                    format!(
+                        "Input '{}' is inside higher-priority entry #{} ('{}') and never applies there",
+                        entry.input,
+                        other_index + 1,
+                        other.input
+                    ),
+                )
+                .related_to(other_index),
+            );
+        }
+    }
+    diagnostics
+}
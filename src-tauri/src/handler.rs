@@ -1,4 +1,4 @@
-use std::net::UdpSocket;
+use std::{net::UdpSocket, ops::Range, sync::Arc};
 
 #[cfg(target_os = "windows")]
 use crate::tsf_conversion::TsfConversion;
@@ -6,7 +6,9 @@ use crate::{
     azookey::{azookey_conversion::AzookeyConversion, client::AzookeyConversionClient},
     config::{Config, OnCopyMode},
     conversion::Conversion,
-    Log, SERVER_NAME, STATE,
+    dictionary::DictionaryStage,
+    dictionary_matcher::{replaced_spans, CompiledDictionary, MatchedSegment},
+    dictionary_template, Log, COMPILED_DICTIONARY, SERVER_NAME, STATE,
 };
 use anyhow::Result;
 use chrono::Local;
@@ -28,6 +30,8 @@ pub struct ConversionHandler {
     clipboard_ctx: ClipboardContext,
     last_text: String,
     last_copy: String,
+    /// Last post-conversion output and the engine output it was made from
+    last_post_conversion: Option<(String, String)>,
 }
 
 impl ConversionHandler {
@@ -48,12 +52,17 @@ impl ConversionHandler {
             clipboard_ctx,
             last_text: String::new(),
             last_copy: String::new(),
+            last_post_conversion: None,
         })
     }
 
     pub fn get_config(&self) -> Config {
         STATE.lock().unwrap().clone()
     }
+
+    pub fn get_dictionary(&self) -> Arc<CompiledDictionary> {
+        COMPILED_DICTIONARY.read().unwrap().clone()
+    }
 }
 
 impl ConversionHandler {
@@ -67,6 +76,58 @@ impl ConversionHandler {
         false
     }
 
+    /// Applies pre-conversion entries for the AzooKey and TSF engines and
+    /// returns where the entry outputs are, so the engines do not convert them
+    /// again
+    fn apply_pre_conversion(&self, text: &str) -> (String, Vec<Range<usize>>) {
+        let segments = self
+            .get_dictionary()
+            .matcher(DictionaryStage::PreConversion)
+            .segments(text);
+        let converted = segments
+            .iter()
+            .map(MatchedSegment::as_str)
+            .collect::<String>();
+        let protected = replaced_spans(&segments);
+        if !protected.is_empty() {
+            info!(
+                "Dictionary conversion (PreConversion): {} -> {}",
+                text, converted
+            );
+        }
+        (converted, protected)
+    }
+
+    fn apply_dictionary(&self, text: &str, stage: DictionaryStage) -> String {
+        let converted = self.get_dictionary().matcher(stage).apply(text);
+        if converted != text {
+            info!(
+                "Dictionary conversion ({:?}): {} -> {}",
+                stage, text, converted
+            );
+        }
+        converted
+    }
+
+    /// Applies post-conversion entries and remembers the engine output, so that
+    /// re-copying the rewritten text still triggers reconversion
+    fn apply_post_conversion(&mut self, converted: String) -> String {
+        let post_converted = self.apply_dictionary(&converted, DictionaryStage::PostConversion);
+        self.last_post_conversion = Some((post_converted.clone(), converted));
+        post_converted
+    }
+
+    /// Maps text produced by `apply_post_conversion` back to the engine output
+    fn restore_engine_output(&self, contents: &str) -> String {
+        match &self.last_post_conversion {
+            Some((post_converted, converted)) if post_converted == contents => {
+                info!("Restoring engine output for reconversion: {}", converted);
+                converted.clone()
+            }
+            _ => contents.to_string(),
+        }
+    }
+
     fn azookey_conversion(&mut self, contents: &str, config: &Config) -> Result<()> {
         if contents.chars().count() > 140 {
             info!("Content exceeds 140 characters, skipping Azookey conversion");
@@ -92,15 +153,30 @@ impl ConversionHandler {
             info!("Azookey conversion created");
         }
 
-        let azookey_conversion = self.azookey_conversion.as_mut().unwrap();
+        let engine_input = self.restore_engine_output(contents);
+        let (dictionary_applied, protected) = self.apply_pre_conversion(&engine_input);
+        let user_dictionary = self.get_dictionary().user_dictionary.clone();
 
-        let converted = azookey_conversion.convert(contents)?;
+        let azookey_conversion = self.azookey_conversion.as_mut().unwrap();
+        azookey_conversion.set_user_dictionary(user_dictionary);
+        azookey_conversion.set_protected_spans(protected);
+
+        let mut warnings = Vec::new();
+        let converted = match azookey_conversion.convert(&dictionary_applied) {
+            Ok(converted) => converted,
+            Err(err) => {
+                error!("Azookey conversion error, keeping the text: {:?}", err);
+                warnings.push(format!("{:#}", err));
+                dictionary_applied
+            }
+        };
+        let converted = self.apply_post_conversion(converted);
 
         info!("Azookey conversion: {} -> {}", contents, converted);
 
         self.last_text = contents.to_string().clone();
 
-        self.return_conversion(contents.to_string(), converted, config);
+        self.return_conversion(contents.to_string(), converted, warnings, config);
 
         Ok(())
     }
@@ -125,20 +201,41 @@ impl ConversionHandler {
             info!("TSF conversion created");
         }
 
-        let tsf_conversion = self.tsf_conversion.as_mut().unwrap();
+        let engine_input = self.restore_engine_output(contents);
+        let (dictionary_applied, protected) = self.apply_pre_conversion(&engine_input);
 
-        let converted = tsf_conversion.convert(contents)?;
+        let tsf_conversion = self.tsf_conversion.as_mut().unwrap();
+        tsf_conversion.set_protected_spans(protected);
+
+        let mut warnings = Vec::new();
+        let converted = match tsf_conversion.convert(&dictionary_applied) {
+            Ok(converted) => converted,
+            Err(err) => {
+                error!("TSF conversion error, keeping the text: {:?}", err);
+                warnings.push(format!("{:#}", err));
+                dictionary_applied
+            }
+        };
+        let converted = self.apply_post_conversion(converted);
 
         info!("TSF conversion: {} -> {}", contents, converted);
 
         self.last_text = contents.to_string().clone();
 
-        self.return_conversion(contents.to_string(), converted, config);
+        self.return_conversion(contents.to_string(), converted, warnings, config);
 
         Ok(())
     }
 
-    fn return_conversion(&mut self, parsed_contents: String, converted: String, config: &Config) {
+    /// Sends the converted text and adds it to the conversion log together
+    /// with the problems that were worked around
+    fn return_conversion(
+        &mut self,
+        parsed_contents: String,
+        converted: String,
+        warnings: Vec<String>,
+        config: &Config,
+    ) {
         match config.on_copy_mode {
             OnCopyMode::ReturnToClipboard => {
                 let mut count = 0;
@@ -189,6 +286,8 @@ impl ConversionHandler {
             }
         }
 
+        dictionary_template::set_last_sent(&converted);
+
         let datetime = Local::now();
         if self
             .app_handle
@@ -198,6 +297,7 @@ impl ConversionHandler {
                     time: datetime.format("%Y %m/%d %H:%M:%S").to_string(),
                     original: parsed_contents,
                     converted,
+                    warnings,
                 },
             )
             .is_err()
@@ -255,21 +355,18 @@ impl ClipboardHandler for ConversionHandler {
                     let parsed_contents = if config.ignore_prefix {
                         contents
                     } else {
-                        contents.split_off(1)
-                    };
-                    let converted = match self.conversion.convert_text(&parsed_contents) {
-                        Ok(converted) => converted,
-                        Err(err) => {
-                            error!("Conversion error: {:?}", err);
-                            format!("Error: {:?}", err)
-                        }
+                        contents.split_off(config.prefix.len())
                     };
+                    let converted = self.conversion.convert_text(&parsed_contents);
+                    let warnings = converted.warnings;
+                    let converted =
+                        self.apply_dictionary(&converted.text, DictionaryStage::PostConversion);
 
                     self.last_text = converted.clone();
 
                     info!("Conversion: {} -> {}", parsed_contents, converted);
 
-                    self.return_conversion(parsed_contents, converted, &config);
+                    self.return_conversion(parsed_contents, converted, warnings, &config);
                 } else {
                     self.last_text = contents;
                 }
@@ -5,8 +5,14 @@ mod azookey;
 mod com;
 mod config;
 mod conversion;
+mod conversion_syntax;
 mod converter;
 mod dictionary;
+mod dictionary_format;
+mod dictionary_layers;
+mod dictionary_matcher;
+mod dictionary_template;
+mod dictionary_validation;
 mod felanguage;
 mod handler;
 mod tauri_emit_subscriber;
@@ -18,9 +24,9 @@ mod vr;
 
 use std::{
     io::{BufRead, BufReader},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Stdio,
-    sync::{Mutex, OnceLock, RwLock},
+    sync::{Arc, Mutex, OnceLock, RwLock},
 };
 
 use azookey::server::AzookeyConversionServer;
@@ -32,7 +38,12 @@ use tauri::{AppHandle, Manager, State};
 use clipboard_master::Master;
 use com::Com;
 use config::Config;
+use converter::converter::ConverterInfo;
 use dictionary::Dictionary;
+use dictionary_format::{DictionaryFormat, DictionaryImportReport};
+use dictionary_layers::{DictionaryLayerInfo, DictionaryLayers};
+use dictionary_matcher::CompiledDictionary;
+use dictionary_validation::DictionaryDiagnostic;
 use handler::ConversionHandler;
 use tauri_emit_subscriber::TauriEmitSubscriber;
 use tauri_plugin_updater::UpdaterExt;
@@ -46,6 +57,9 @@ struct Log {
     pub time: String,
     pub original: String,
     pub converted: String,
+    /// Syntax and conversion errors that were kept as text
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 struct AppState {
@@ -55,6 +69,8 @@ struct AppState {
 
 static STATE: Lazy<Mutex<Config>> = Lazy::new(|| Mutex::new(Config::load().unwrap()));
 static DICTIONARY: Lazy<Mutex<Dictionary>> = Lazy::new(|| Mutex::new(Dictionary::load().unwrap()));
+static COMPILED_DICTIONARY: Lazy<RwLock<Arc<CompiledDictionary>>> =
+    Lazy::new(|| RwLock::new(Arc::new(compile_dictionary(&DICTIONARY.lock().unwrap()))));
 static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 static SERVER_PROCESS: Lazy<Mutex<Option<std::process::Child>>> = Lazy::new(|| Mutex::new(None));
 
@@ -97,12 +113,29 @@ fn check_tsf_availability_command() -> Result<bool, String> {
     }
 }
 
+fn compile_dictionary(personal: &Dictionary) -> CompiledDictionary {
+    let merged = match DictionaryLayers::load() {
+        Ok(layers) => layers.merge(personal),
+        Err(e) => {
+            error!("Failed to load dictionary layers: {}", e);
+            personal.clone()
+        }
+    };
+    CompiledDictionary::new(&merged)
+}
+
+fn update_dictionary(dictionary: &Dictionary) {
+    *DICTIONARY.lock().unwrap() = dictionary.clone();
+    *COMPILED_DICTIONARY.write().unwrap() = Arc::new(compile_dictionary(dictionary));
+}
+
 #[tauri::command]
 fn load_dictionary(state: State<AppState>) -> Result<Dictionary, String> {
     match Dictionary::load() {
         Ok(dictionary) => {
             let mut app_dictionary = state.dictionary.lock().unwrap();
             *app_dictionary = dictionary.clone();
+            update_dictionary(&dictionary);
             Ok(dictionary)
         }
         Err(e) => Err(format!("Failed to load dictionary: {}", e)),
@@ -110,11 +143,148 @@ fn load_dictionary(state: State<AppState>) -> Result<Dictionary, String> {
 }
 
 #[tauri::command]
-fn save_dictionary(dictionary: Dictionary, state: State<AppState>) -> Result<(), String> {
-    *DICTIONARY.lock().unwrap() = dictionary.clone();
+fn validate_dictionary(dictionary: Dictionary) -> Result<Vec<DictionaryDiagnostic>, String> {
+    Ok(dictionary_validation::validate(&dictionary))
+}
+
+#[tauri::command]
+fn list_converters() -> Vec<ConverterInfo> {
+    converter::converter::list_converters()
+}
+
+/// Refuses dictionaries with validation errors, listing them in the message
+fn check_dictionary(dictionary: &Dictionary) -> Result<(), String> {
+    let errors = dictionary_validation::validate(dictionary)
+        .into_iter()
+        .filter(|d| d.is_error())
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    error!("Refusing to save dictionary with {} errors", errors.len());
+    Err(format!(
+        "Dictionary has {} errors: {}",
+        errors.len(),
+        errors
+            .iter()
+            .map(|d| format!("#{}: {}", d.index + 1, d.message))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[tauri::command]
+fn save_dictionary(
+    dictionary: Dictionary,
+    force: Option<bool>,
+    state: State<AppState>,
+) -> Result<(), String> {
+    if !force.unwrap_or(false) {
+        check_dictionary(&dictionary)?;
+    }
+    update_dictionary(&dictionary);
     dictionary.save(state).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn import_dictionary(
+    path: String,
+    format: DictionaryFormat,
+    state: State<AppState>,
+) -> Result<DictionaryImportReport, String> {
+    let import = format
+        .import_file(Path::new(&path))
+        .map_err(|e| format!("Failed to import dictionary: {}", e))?;
+    // Entries that are already saved were accepted before and are not checked again
+    check_dictionary(&Dictionary {
+        entries: import.entries.clone(),
+        ..Default::default()
+    })?;
+    let mut dictionary = state.dictionary.lock().unwrap().clone();
+    let report = import.merge_into(&mut dictionary);
+    update_dictionary(&dictionary);
+    dictionary.save(state)?;
+    Ok(report)
+}
+
+#[tauri::command]
+fn export_dictionary(
+    path: String,
+    format: DictionaryFormat,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    let dictionary = state.dictionary.lock().unwrap().clone();
+    format
+        .export_file(&dictionary, Path::new(&path))
+        .map_err(|e| format!("Failed to export dictionary: {}", e))
+}
+
+#[tauri::command]
+fn list_dictionary_layers() -> Result<Vec<DictionaryLayerInfo>, String> {
+    let layers =
+        DictionaryLayers::load().map_err(|e| format!("Failed to load dictionary layers: {}", e))?;
+    layers
+        .save()
+        .map_err(|e| format!("Failed to save dictionary layers: {}", e))?;
+    Ok(layers.list())
+}
+
+#[tauri::command]
+fn set_dictionary_layer_enabled(file: String, enabled: bool) -> Result<(), String> {
+    let mut layers =
+        DictionaryLayers::load().map_err(|e| format!("Failed to load dictionary layers: {}", e))?;
+    layers
+        .set_enabled(&file, enabled)
+        .map_err(|e| e.to_string())?;
+    layers
+        .save()
+        .map_err(|e| format!("Failed to save dictionary layers: {}", e))?;
+    let personal = DICTIONARY.lock().unwrap().clone();
+    update_dictionary(&personal);
+    Ok(())
+}
+
+#[tauri::command]
+fn reorder_dictionary_layers(files: Vec<String>) -> Result<(), String> {
+    let mut layers =
+        DictionaryLayers::load().map_err(|e| format!("Failed to load dictionary layers: {}", e))?;
+    layers.reorder(&files).map_err(|e| e.to_string())?;
+    layers
+        .save()
+        .map_err(|e| format!("Failed to save dictionary layers: {}", e))?;
+    let personal = DICTIONARY.lock().unwrap().clone();
+    update_dictionary(&personal);
+    Ok(())
+}
+
+#[tauri::command]
+fn add_dictionary_layer(path: String) -> Result<(), String> {
+    let mut layers =
+        DictionaryLayers::load().map_err(|e| format!("Failed to load dictionary layers: {}", e))?;
+    layers
+        .add_external(Path::new(&path))
+        .map_err(|e| e.to_string())?;
+    layers
+        .save()
+        .map_err(|e| format!("Failed to save dictionary layers: {}", e))?;
+    let personal = DICTIONARY.lock().unwrap().clone();
+    update_dictionary(&personal);
+    Ok(())
+}
+
+#[tauri::command]
+fn remove_dictionary_layer(file: String) -> Result<(), String> {
+    let mut layers =
+        DictionaryLayers::load().map_err(|e| format!("Failed to load dictionary layers: {}", e))?;
+    layers.remove_external(&file).map_err(|e| e.to_string())?;
+    layers
+        .save()
+        .map_err(|e| format!("Failed to save dictionary layers: {}", e))?;
+    let personal = DICTIONARY.lock().unwrap().clone();
+    update_dictionary(&personal);
+    Ok(())
+}
+
 #[tauri::command]
 fn open_ms_settings_regionlanguage_jpnime() -> Result<(), String> {
     let _ = std::process::Command::new("cmd")
@@ -262,6 +432,15 @@ async fn main() {
             open_ms_settings_regionlanguage_jpnime,
             load_dictionary,
             save_dictionary,
+            validate_dictionary,
+            import_dictionary,
+            export_dictionary,
+            list_dictionary_layers,
+            set_dictionary_layer_enabled,
+            reorder_dictionary_layers,
+            add_dictionary_layer,
+            remove_dictionary_layer,
+            list_converters,
             check_update,
             register_manifest,
         ])
@@ -1,8 +1,12 @@
+#[cfg(target_os = "windows")]
+use std::ops::Range;
+
 #[cfg(target_os = "windows")]
 use crate::{
     converter::{
         converter::Converter, hiragana::HiraganaConverter, roman_to_kanji::RomanToKanjiConverter,
     },
+    dictionary_matcher::convert_around,
     tsf::{search_candidate_provider::SearchCandidateProvider, set_thread_local_input_settings},
 };
 use anyhow::Result;
@@ -18,6 +22,8 @@ pub struct TsfConversion {
     pub reconversion_candidates: Option<Vec<String>>,
     pub reconversion_index: Option<i32>,
     pub reconversion_prefix: Option<String>,
+    /// Byte ranges of pre-conversion dictionary outputs kept as they are
+    pub protected_spans: Vec<Range<usize>>,
 }
 
 #[cfg(target_os = "windows")]
@@ -35,10 +41,16 @@ impl TsfConversion {
             reconversion_candidates: None,
             reconversion_index: None,
             reconversion_prefix: None,
+            protected_spans: Vec::new(),
         };
         instance
     }
 
+    /// Sets the text that the next conversion passes through unchanged
+    pub fn set_protected_spans(&mut self, protected_spans: Vec<Range<usize>>) {
+        self.protected_spans = protected_spans;
+    }
+
     fn reset_conversion_state(&mut self) {
         debug!("Resetting conversion state");
         trace!("Before reset - now_reconvertion: {}, reconversion_prefix: {:?}, reconversion_index: {:?}, reconversion_candidates: {:?}",
@@ -79,7 +91,11 @@ impl TsfConversion {
         debug!("Difference to convert: {}", diff);
 
         let roman_to_kanji_converter = RomanToKanjiConverter;
-        let converted = roman_to_kanji_converter.convert(&diff)?;
+        let protected_spans = std::mem::take(&mut self.protected_spans);
+        let diff_offset = text.len() - diff.len();
+        let converted = convert_around(&diff, diff_offset, &protected_spans, |segment| {
+            roman_to_kanji_converter.convert(segment)
+        })?;
         trace!("Converted difference: {}", converted);
         self.conversion_history.push(
             o_minus_1
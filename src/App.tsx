@@ -19,6 +19,8 @@ interface Log {
   time: string;
   original: string;
   converted: string;
+  // 変換できずにそのまま送った部分の理由
+  warnings: string[];
 }
 
 const AppContent = () => {
@@ -38,7 +40,7 @@ const AppContent = () => {
 
   useEffect(() => {
     const unlisten = listen<Log>('addLog', (event) => {
-      setLogs(prevLogs => [{ time: event.payload.time, original: event.payload.original, converted: event.payload.converted }, ...prevLogs]);
+      setLogs(prevLogs => [{ time: event.payload.time, original: event.payload.original, converted: event.payload.converted, warnings: event.payload.warnings ?? [] }, ...prevLogs]);
     });
 
     return () => {
@@ -152,7 +154,7 @@ const AppContent = () => {
     setUpdateAvailable(false);
   };
 
-  const renderLogEntry = (log: { time: string; original: string; converted: string }, index: number) => (
+  const renderLogEntry = (log: Log, index: number) => (
     <div key={index} className="mb-2 p-2 bg-white/90 dark:bg-gray-800 rounded border border-gray-100 dark:border-gray-700 text-sm transition-colors">
       <div className="text-xs text-gray-500 dark:text-gray-400 mb-1">{log.time}</div>
       <div className="flex flex-col sm:flex-row sm:items-center gap-1">
@@ -160,6 +162,9 @@ const AppContent = () => {
         <div className="text-gray-400 hidden sm:block text-xs">→</div>
         <div className="text-emerald-600 dark:text-emerald-400 px-1.5 py-0.5 bg-emerald-50 dark:bg-emerald-900/30 rounded flex-grow transition-colors">{log.converted}</div>
       </div>
+      {log.warnings.map((warning, warningIndex) => (
+        <div key={warningIndex} className="mt-1 text-xs text-amber-600 dark:text-amber-400">⚠ {warning}</div>
+      ))}
     </div>
   );
 
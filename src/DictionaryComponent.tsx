@@ -5,12 +5,15 @@ import {
   Dictionary, 
   DictionaryEntry, 
   ConversionMethod, 
-  availableConverters, 
+  ConverterInfo,
+  DictionaryDiagnostic,
+  RustDictionary,
   getDefaultDictionaryEntry, 
   getConverterInfo,
   convertToRustEntry,
   convertFromRustEntry
 } from './types/dictionary';
+import DictionaryLayersComponent from './DictionaryLayersComponent';
 
 // 入力フィールド用の内部コンポーネント（フォーカスを保持するため）
 const InputField: React.FC<{
@@ -134,7 +137,11 @@ const DictionaryComponent: React.FC = () => {
   const [showDialog, setShowDialog] = useState(false);
   const [isMethodDropdownOpen, setIsMethodDropdownOpen] = useState(false);
   const [isConverterDropdownOpen, setIsConverterDropdownOpen] = useState(false);
+  const [converters, setConverters] = useState<ConverterInfo[]>([]);
   const [saveStatus, setSaveStatus] = useState<'idle' | 'saving' | 'success' | 'error'>('idle');
+  const [saveError, setSaveError] = useState<string | null>(null);
+  // 編集中のエントリの問題（エラーがあると保存前に表示する）
+  const [diagnostics, setDiagnostics] = useState<DictionaryDiagnostic[]>([]);
   
   // 編集中のエントリを更新するためのフォースレンダリング用state
   const [, forceUpdate] = useState({});
@@ -148,6 +155,7 @@ const DictionaryComponent: React.FC = () => {
   // 辞書データの読み込み
   useEffect(() => {
     loadDictionary();
+    loadConverters();
   }, []);
 
   // ドロップダウン外のクリックを検知して閉じる
@@ -181,33 +189,56 @@ const DictionaryComponent: React.FC = () => {
         return convertedEntry;
       });
       
-      setDictionary({ entries: entriesWithPriority });
+      setDictionary({
+        name: loadedDictionary.name ?? undefined,
+        author: loadedDictionary.author ?? undefined,
+        version: loadedDictionary.version ?? undefined,
+        entries: entriesWithPriority
+      });
     } catch (error) {
       console.error('Failed to load dictionary:', error);
     }
   };
 
-  const saveDictionary = async (dict: Dictionary) => {
-    setSaveStatus('saving');
+  const loadConverters = async () => {
     try {
-      // TypeScript形式からRust形式に変換
-      const rustDict = {
-        entries: dict.entries.map(entry => convertToRustEntry(entry))
-      };
+      const loadedConverters: ConverterInfo[] = await invoke('list_converters');
+      setConverters(loadedConverters);
+    } catch (error) {
+      console.error('Failed to load converters:', error);
+    }
+  };
+
+  // TypeScript形式からRust形式に変換（name / author / version も消さずに送る）
+  const toRustDictionary = (dict: Dictionary): RustDictionary => ({
+    name: dict.name,
+    author: dict.author,
+    version: dict.version,
+    entries: dict.entries.map(entry => convertToRustEntry(entry))
+  });
 
-      await invoke('save_dictionary', { dictionary: rustDict });
+  // force を指定すると、エラーのあるエントリが含まれていても保存する
+  const saveDictionary = async (dict: Dictionary, force = false) => {
+    setSaveStatus('saving');
+    try {
+      await invoke('save_dictionary', { dictionary: toRustDictionary(dict), force });
+      setSaveError(null);
       setSaveStatus('success');
       setTimeout(() => setSaveStatus('idle'), 2000);
+      return true;
     } catch (error) {
       console.error('Failed to save dictionary:', error);
+      setSaveError(String(error));
       setSaveStatus('error');
-      setTimeout(() => setSaveStatus('idle'), 3000);
+      setTimeout(() => setSaveStatus('idle'), 5000);
+      return false;
     }
   };
 
   const handleAddEntry = () => {
     entryRef.current = getDefaultDictionaryEntry();
     setEditIndex(null);
+    setDiagnostics([]);
     setShowDialog(true);
     // 強制的に再レンダリング
     forceUpdate({});
@@ -216,6 +247,7 @@ const DictionaryComponent: React.FC = () => {
   const handleEditEntry = (entry: DictionaryEntry, index: number) => {
     entryRef.current = {...entry};
     setEditIndex(index);
+    setDiagnostics([]);
     setShowDialog(true);
     // 強制的に再レンダリング
     forceUpdate({});
@@ -226,31 +258,46 @@ const DictionaryComponent: React.FC = () => {
     newEntries.splice(index, 1);
     const newDict = { ...dictionary, entries: newEntries };
     setDictionary(newDict);
-    saveDictionary(newDict);
+    // 削除で新しいエラーは増えないので、保存済みのエラーでは止めない
+    saveDictionary(newDict, true);
   };
 
-  const handleSaveEntry = () => {
+  const handleSaveEntry = async (force = false) => {
     if (!entryRef.current) return;
     
     const newEntries = [...dictionary.entries];
-    
-    // 新規追加の場合
-    if (editIndex === null) {
-      newEntries.push(entryRef.current);
-    } else {
-      // 編集の場合
-      newEntries[editIndex] = entryRef.current;
-    }
-    
+    // 新規追加の場合は末尾、編集の場合は元の位置
+    const index = editIndex ?? newEntries.length;
+    newEntries[index] = entryRef.current;
     const newDict = { ...dictionary, entries: newEntries };
-    setDictionary(newDict);
-    saveDictionary(newDict);
-    
-    // ダイアログを閉じて状態をリセット
-    closeDialog();
+
+    // 他のエントリの問題は保存済みのものなので、編集中のエントリだけを確認する
+    let checked = force;
+    if (!force) {
+      try {
+        const found: DictionaryDiagnostic[] = await invoke('validate_dictionary', {
+          dictionary: toRustDictionary(newDict)
+        });
+        const entryDiagnostics = found.filter(diagnostic => diagnostic.index === index);
+        if (entryDiagnostics.some(diagnostic => diagnostic.severity === 'Error')) {
+          setDiagnostics(entryDiagnostics);
+          return;
+        }
+        checked = true;
+      } catch (error) {
+        console.error('Failed to validate dictionary:', error);
+      }
+    }
+
+    // 確認できなかったときは保存時の確認に任せる。保存できたときだけ一覧に反映する
+    if (await saveDictionary(newDict, checked)) {
+      setDictionary(newDict);
+      closeDialog();
+    }
   };
   
   const closeDialog = () => {
+    setDiagnostics([]);
     setShowDialog(false);
     setIsMethodDropdownOpen(false);
     setIsConverterDropdownOpen(false);
@@ -285,7 +332,8 @@ const DictionaryComponent: React.FC = () => {
     
     const newDict = { ...dictionary, entries: newEntries };
     setDictionary(newDict);
-    saveDictionary(newDict);
+    // 並べ替えで新しいエラーは増えないので、保存済みのエラーでは止めない
+    saveDictionary(newDict, true);
   };
 
   // 入力フィールドの値を更新（フォーカスを失わないようRef経由で更新）
@@ -335,8 +383,8 @@ const DictionaryComponent: React.FC = () => {
         return '無変換';
       case ConversionMethod.Converter:
         if (converterChar) {
-          const converter = getConverterInfo(converterChar);
-          return converter ? `変換: ${converter.name}` : '変換';
+          const converter = getConverterInfo(converters, converterChar);
+          return converter ? `変換: ${converter.display_name}` : '変換';
         }
         return '変換';
       default:
@@ -351,7 +399,11 @@ const DictionaryComponent: React.FC = () => {
       case 'success':
         return <span className="flex items-center text-green-500 text-xs"><Check size={10} className="mr-0.5" /> 保存完了</span>;
       case 'error':
-        return <span className="flex items-center text-red-500 text-xs"><AlertCircle size={10} className="mr-0.5" /> 保存失敗</span>;
+        return (
+          <span className="flex items-center text-red-500 text-xs max-w-xs truncate" title={saveError ?? undefined}>
+            <AlertCircle size={10} className="mr-0.5 flex-shrink-0" /> 保存失敗{saveError && `: ${saveError}`}
+          </span>
+        );
       default:
         return null;
     }
@@ -362,6 +414,7 @@ const DictionaryComponent: React.FC = () => {
     if (!showDialog || !entryRef.current) return null;
     
     const entry = entryRef.current;
+    const hasErrors = diagnostics.some(diagnostic => diagnostic.severity === 'Error');
 
     return (
       <div className="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-50">
@@ -392,6 +445,34 @@ const DictionaryComponent: React.FC = () => {
               checked={entry.use_regex}
               onChange={(checked) => handleChangeEntryField('use_regex', checked)}
             />
+
+            <CheckboxField
+              id="case_insensitive"
+              label="大文字と小文字を区別しない"
+              checked={entry.case_insensitive}
+              onChange={(checked) => handleChangeEntryField('case_insensitive', checked)}
+            />
+
+            <CheckboxField
+              id="whole_word"
+              label="単語単位で一致"
+              checked={entry.whole_word}
+              onChange={(checked) => handleChangeEntryField('whole_word', checked)}
+            />
+
+            <CheckboxField
+              id="whole_message"
+              label="メッセージ全体と一致"
+              checked={entry.whole_message}
+              onChange={(checked) => handleChangeEntryField('whole_message', checked)}
+            />
+
+            <CheckboxField
+              id="post_conversion"
+              label="変換後に適用"
+              checked={entry.stage === 'PostConversion'}
+              onChange={(checked) => handleChangeEntryField('stage', checked ? 'PostConversion' : 'PreConversion')}
+            />
             
             <div className="relative mb-3" ref={methodDropdownRef}>
               <label className="block text-xs font-medium text-gray-700 dark:text-gray-300 mb-1">
@@ -454,25 +535,31 @@ const DictionaryComponent: React.FC = () => {
                 >
                   <span>
                     {entry.converter_char 
-                      ? getConverterInfo(entry.converter_char)?.name || 'ローマ字→漢字'
+                      ? getConverterInfo(converters, entry.converter_char)?.display_name || 'ローマ字→漢字'
                       : 'ローマ字→漢字'}
                   </span>
                   <ChevronDown size={14} className={`transition-transform ${isConverterDropdownOpen ? 'transform rotate-180' : ''}`} />
                 </div>
                 {isConverterDropdownOpen && (
                   <div className="absolute z-10 mt-0.5 w-full bg-white dark:bg-gray-700 border border-gray-200 dark:border-gray-600 rounded shadow-sm overflow-hidden text-sm transition-colors">
-                    {availableConverters.map(converter => (
+                    {converters.filter(converter => converter.trigger).map(converter => (
                       <div
-                        key={converter.id}
+                        key={converter.trigger!}
                         className="p-1.5 hover:bg-indigo-50 dark:hover:bg-indigo-900/50 cursor-pointer"
-                        onClick={() => handleSelectConverter(converter.id)}
+                        onClick={() => handleSelectConverter(converter.trigger!)}
                       >
-                        <div className={`flex items-center ${entry.converter_char === converter.id ? 'text-indigo-600 dark:text-indigo-400 font-medium' : 'dark:text-gray-300'}`}>
-                          {entry.converter_char === converter.id && <Check size={12} className="mr-1.5" />}
-                          <span className={entry.converter_char === converter.id ? 'ml-0' : 'ml-4'}>
-                            {converter.name} - {converter.description}
+                        <div className={`flex items-center ${entry.converter_char === converter.trigger ? 'text-indigo-600 dark:text-indigo-400 font-medium' : 'dark:text-gray-300'}`}>
+                          {entry.converter_char === converter.trigger && <Check size={12} className="mr-1.5" />}
+                          <span className={entry.converter_char === converter.trigger ? 'ml-0' : 'ml-4'}>
+                            {converter.display_name} - {converter.description}
+                            {converter.aliases.length > 0 && ` (${converter.aliases.join(', ')})`}
                           </span>
                         </div>
+                        {converter.examples.length > 0 && (
+                          <div className="ml-4 text-xs text-gray-500 dark:text-gray-400">
+                            {converter.examples.map(example => `${example.input} → ${example.output}`).join(' / ')}
+                          </div>
+                        )}
                       </div>
                     ))}
                   </div>
@@ -484,10 +571,16 @@ const DictionaryComponent: React.FC = () => {
               <InputField
                 label="置き換え後の文字列"
                 value={entry.output || ''}
-                placeholder="例: Hello"
+                placeholder={entry.use_regex ? '例: $1時' : '例: Hello'}
                 onChange={(value) => handleChangeEntryField('output', value)}
               />
             )}
+
+            {entry.method === ConversionMethod.Replace && (
+              <p className="-mt-2 mb-3 text-xs text-gray-500 dark:text-gray-400">
+                {'{time} {date:%m/%d} {time:America/New_York} {last_sent} {counter:名前} {random:a|b|c} が使えます。{{ と }} で波括弧そのものを出力します。'}
+              </p>
+            )}
             
             <NumberField
               label="優先順位"
@@ -496,6 +589,25 @@ const DictionaryComponent: React.FC = () => {
               description="数値が大きいほど優先度が高くなります。"
             />
           </div>
+
+          {diagnostics.length > 0 && (
+            <div className="mt-4 p-2 rounded border border-red-200 dark:border-red-800 bg-red-50 dark:bg-red-900/30 text-xs transition-colors">
+              <p className="flex items-center font-medium text-red-600 dark:text-red-400 mb-1">
+                <AlertCircle size={12} className="mr-1" />
+                このエントリには問題があります
+              </p>
+              <ul className="list-disc ml-5 space-y-0.5">
+                {diagnostics.map((diagnostic, index) => (
+                  <li
+                    key={index}
+                    className={diagnostic.severity === 'Error' ? 'text-red-600 dark:text-red-400' : 'text-yellow-600 dark:text-yellow-400'}
+                  >
+                    {diagnostic.message}
+                  </li>
+                ))}
+              </ul>
+            </div>
+          )}
           
           <div className="mt-6 flex justify-end">
             <button
@@ -504,8 +616,16 @@ const DictionaryComponent: React.FC = () => {
             >
               キャンセル
             </button>
+            {hasErrors && (
+              <button
+                onClick={() => handleSaveEntry(true)}
+                className="mr-2 px-3 py-1.5 rounded text-sm bg-red-500 hover:bg-red-600 text-white dark:bg-red-600 dark:hover:bg-red-700"
+              >
+                それでも保存
+              </button>
+            )}
             <button
-              onClick={handleSaveEntry}
+              onClick={() => handleSaveEntry()}
               className="px-3 py-1.5 rounded text-sm bg-indigo-500 hover:bg-indigo-600 text-white dark:bg-indigo-600 dark:hover:bg-indigo-700"
             >
               保存
@@ -536,6 +656,7 @@ const DictionaryComponent: React.FC = () => {
         </div>
       </div>
 
+      <DictionaryLayersComponent />
 
       <div className="bg-white dark:bg-gray-800 rounded border border-gray-100 dark:border-gray-700 p-3 transition-colors">
         {dictionary.entries.length > 0 ? (
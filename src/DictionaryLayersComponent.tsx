@@ -0,0 +1,166 @@
+import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Layers, Plus, Trash, ChevronUp, ChevronDown, AlertCircle } from 'lucide-react';
+import { DictionaryLayerInfo } from './types/dictionary';
+
+// 辞書レイヤー（個人辞書と共有辞書ファイル）の有効・無効と順番を管理する
+// 上にあるレイヤーほど、同じ優先度のエントリで優先される
+const DictionaryLayersComponent: React.FC = () => {
+  const [layers, setLayers] = useState<DictionaryLayerInfo[]>([]);
+  const [newPath, setNewPath] = useState('');
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    loadLayers();
+  }, []);
+
+  const loadLayers = async () => {
+    try {
+      const loadedLayers: DictionaryLayerInfo[] = await invoke('list_dictionary_layers');
+      setLayers(loadedLayers);
+    } catch (error) {
+      console.error('Failed to load dictionary layers:', error);
+      setError(String(error));
+    }
+  };
+
+  // 操作のあとは一覧を読み直して、エントリ数や読み込みエラーを反映する
+  const runCommand = async (command: string, args: Record<string, unknown>) => {
+    try {
+      await invoke(command, args);
+      setError(null);
+      return true;
+    } catch (error) {
+      console.error(`Failed to run ${command}:`, error);
+      setError(String(error));
+      return false;
+    } finally {
+      await loadLayers();
+    }
+  };
+
+  const handleToggle = (layer: DictionaryLayerInfo) => {
+    runCommand('set_dictionary_layer_enabled', { file: layer.file, enabled: !layer.enabled });
+  };
+
+  const handleMove = (index: number, direction: 'up' | 'down') => {
+    const target = direction === 'up' ? index - 1 : index + 1;
+    if (target < 0 || target >= layers.length) return;
+    const files = layers.map(layer => layer.file);
+    [files[index], files[target]] = [files[target], files[index]];
+    runCommand('reorder_dictionary_layers', { files });
+  };
+
+  const handleAdd = async () => {
+    const path = newPath.trim();
+    if (!path) return;
+    if (await runCommand('add_dictionary_layer', { path })) {
+      setNewPath('');
+    }
+  };
+
+  const handleRemove = (layer: DictionaryLayerInfo) => {
+    runCommand('remove_dictionary_layer', { file: layer.file });
+  };
+
+  const getLayerLabel = (layer: DictionaryLayerInfo) => {
+    if (layer.personal) return '個人辞書';
+    return layer.name ?? layer.file;
+  };
+
+  const getLayerDetails = (layer: DictionaryLayerInfo) => {
+    const details = [];
+    if (layer.author) details.push(`作者: ${layer.author}`);
+    if (layer.version) details.push(`v${layer.version}`);
+    details.push(`${layer.entry_count}件`);
+    if (layer.invalid_entry_count > 0) details.push(`エラーで無効: ${layer.invalid_entry_count}件`);
+    return details.join(' · ');
+  };
+
+  return (
+    <div className="bg-white dark:bg-gray-800 rounded border border-gray-100 dark:border-gray-700 p-3 mb-2 transition-colors">
+      <h3 className="text-sm font-medium text-gray-700 dark:text-gray-200 flex items-center mb-2">
+        <Layers size={14} className="mr-1.5" />
+        辞書レイヤー
+      </h3>
+
+      <ul className="space-y-1">
+        {layers.map((layer, index) => (
+          <li key={layer.file} className="flex items-center text-xs text-gray-700 dark:text-gray-300">
+            <input
+              type="checkbox"
+              checked={layer.enabled}
+              onChange={() => handleToggle(layer)}
+              className="h-3.5 w-3.5 mr-2 text-indigo-500 border-gray-300 dark:border-gray-600 rounded dark:bg-gray-700"
+              title={layer.enabled ? '無効にする' : '有効にする'}
+            />
+            <div className="flex-1 min-w-0">
+              <div className="truncate" title={layer.file}>{getLayerLabel(layer)}</div>
+              {layer.error ? (
+                <div className="flex items-center text-red-500 truncate" title={layer.error}>
+                  <AlertCircle size={10} className="mr-0.5 flex-shrink-0" /> {layer.error}
+                </div>
+              ) : (
+                <div className="text-gray-500 dark:text-gray-400">{getLayerDetails(layer)}</div>
+              )}
+            </div>
+            <div className="flex flex-col ml-2">
+              <button
+                onClick={() => handleMove(index, 'up')}
+                className="text-gray-500 hover:text-indigo-500 disabled:opacity-30"
+                disabled={index === 0}
+                title="上へ"
+              >
+                <ChevronUp size={14} />
+              </button>
+              <button
+                onClick={() => handleMove(index, 'down')}
+                className="text-gray-500 hover:text-indigo-500 disabled:opacity-30"
+                disabled={index === layers.length - 1}
+                title="下へ"
+              >
+                <ChevronDown size={14} />
+              </button>
+            </div>
+            {layer.external && (
+              <button
+                onClick={() => handleRemove(layer)}
+                className="text-red-500 hover:text-red-600 dark:text-red-400 dark:hover:text-red-300 p-1 ml-1"
+                title="一覧から外す（ファイルは削除しません）"
+              >
+                <Trash size={14} />
+              </button>
+            )}
+          </li>
+        ))}
+      </ul>
+
+      <div className="flex items-center mt-2">
+        <input
+          type="text"
+          value={newPath}
+          onChange={(e) => setNewPath(e.target.value)}
+          onKeyDown={(e) => e.key === 'Enter' && handleAdd()}
+          placeholder="共有辞書ファイルの絶対パス (例: C:\Users\me\Dropbox\team.yaml)"
+          className="flex-1 px-2 py-1 text-xs border border-gray-200 dark:border-gray-600 rounded bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
+        />
+        <button
+          onClick={handleAdd}
+          disabled={!newPath.trim()}
+          className="flex items-center ml-2 text-xs text-white bg-indigo-500 hover:bg-indigo-600 dark:bg-indigo-600 dark:hover:bg-indigo-700 px-2 py-1 rounded disabled:opacity-50"
+        >
+          <Plus size={12} className="mr-1" />
+          追加
+        </button>
+      </div>
+
+      {error && (
+        <p className="flex items-center mt-1.5 text-xs text-red-500" title={error}>
+          <AlertCircle size={10} className="mr-0.5 flex-shrink-0" /> {error}
+        </p>
+      )}
+    </div>
+  );
+};
+
+export default DictionaryLayersComponent;
@@ -1,3 +1,5 @@
+export type DictionaryStage = 'PreConversion' | 'PostConversion';
+
 export enum ConversionMethod {
     Replace = 'Replace',
     None = 'None',
@@ -10,6 +12,10 @@ export interface DictionaryEntry {
     output?: string;
     use_regex: boolean;
     priority: number;
+    case_insensitive: boolean;
+    whole_word: boolean;
+    whole_message: boolean;
+    stage: DictionaryStage;
     converter_char?: string;
 }
 
@@ -19,32 +25,67 @@ export interface RustDictionaryEntry {
     output?: string;
     use_regex: boolean;
     priority: number;
+    case_insensitive: boolean;
+    whole_word: boolean;
+    whole_message: boolean;
+    stage: DictionaryStage;
 }
 
 export interface RustDictionary {
+    name?: string;
+    author?: string;
+    version?: string;
     entries: RustDictionaryEntry[];
 }
 
+// name / author / version は保存時にそのまま書き戻す
 export interface Dictionary {
+    name?: string;
+    author?: string;
+    version?: string;
     entries: DictionaryEntry[];
   }
 
+// list_dictionary_layers コマンドが返す辞書レイヤーの情報
+export interface DictionaryLayerInfo {
+  file: string;
+  enabled: boolean;
+  personal: boolean;
+  external: boolean;
+  name: string | null;
+  author: string | null;
+  version: string | null;
+  entry_count: number;
+  invalid_entry_count: number;
+  error: string | null;
+}
+
+// validate_dictionary コマンドが返す辞書エントリの問題
+export interface DictionaryDiagnostic {
+  index: number;
+  kind: string;
+  severity: 'Error' | 'Warning';
+  message: string;
+  related_index: number | null;
+}
+
+export interface ConverterExample {
+  input: string;
+  output: string;
+}
+
+// list_converters コマンドが返す変換器の情報
 export interface ConverterInfo {
-  id: string;
+  trigger: string | null;
   name: string;
+  aliases: string[];
+  display_name: string;
   description: string;
+  examples: ConverterExample[];
 }
 
-export const availableConverters: ConverterInfo[] = [
-  { id: 'r', name: 'ローマ字→漢字', description: 'ローマ字を漢字に変換します' },
-  { id: 'h', name: 'ひらがな変換', description: '入力をひらがなに変換します' },
-  { id: 'k', name: 'カタカナ変換', description: '入力をカタカナに変換します' },
-  { id: 'c', name: '計算', description: '数式を計算します' },
-  { id: 'n', name: '無変換', description: '入力をそのまま出力します' },
-];
-
-export function getConverterInfo(id: string): ConverterInfo | undefined {
-  return availableConverters.find(converter => converter.id === id);
+export function getConverterInfo(converters: ConverterInfo[], trigger: string): ConverterInfo | undefined {
+  return converters.find(converter => converter.trigger === trigger);
 }
 
 export function getDefaultDictionaryEntry(): DictionaryEntry {
@@ -53,7 +94,11 @@ export function getDefaultDictionaryEntry(): DictionaryEntry {
     method: ConversionMethod.Replace,
     output: '',
     use_regex: false,
-    priority: 0
+    priority: 0,
+    case_insensitive: false,
+    whole_word: false,
+    whole_message: false,
+    stage: 'PreConversion'
   };
 }
 
@@ -71,7 +116,11 @@ export function convertToRustEntry(entry: DictionaryEntry): RustDictionaryEntry
         method: method,
         output: entry.method === ConversionMethod.Replace ? entry.output : undefined,
         use_regex: entry.use_regex,
-        priority: entry.priority
+        priority: entry.priority,
+        case_insensitive: entry.case_insensitive,
+        whole_word: entry.whole_word,
+        whole_message: entry.whole_message,
+        stage: entry.stage
     };
 }
 
@@ -92,6 +141,10 @@ export function convertFromRustEntry(entry: any): DictionaryEntry {
         output: entry.output,
         use_regex: entry.use_regex,
         priority: entry.priority,
+        case_insensitive: entry.case_insensitive ?? false,
+        whole_word: entry.whole_word ?? false,
+        whole_message: entry.whole_message ?? false,
+        stage: entry.stage ?? 'PreConversion',
         converter_char: converter_char
     };
 }
\ No newline at end of file